@@ -1,29 +1,38 @@
 #![allow(unused)]
 
 use std::{
-    sync::{mpsc, Arc, Mutex},
+    any::Any,
+    collections::VecDeque,
+    error::Error,
+    fmt, mem,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc, Condvar, Mutex, MutexGuard, PoisonError,
+    },
     thread,
+    time::{Duration, Instant},
 };
 
 pub struct ThreadPool {
-    workers: Vec<Worker>,
-    sender: Option<mpsc::Sender<Job>>,
+    // every worker started so far; the ones that retired are cleared out whenever another one is added
+    workers: Mutex<Vec<Worker>>,
+    queue: Arc<Queue>,
+    // how many jobs may wait in the queue, None for no limit
+    queue_capacity: Option<usize>,
+    queue_policy: QueuePolicy,
+    // how many jobs have panicked so far, shared with the workers that catch them
+    panics: Arc<AtomicUsize>,
 }
 
 impl Drop for ThreadPool {
     fn drop(&mut self) {
-        // explicitly execute the drop method to close the channel so no more messages will be sent
-        drop(self.sender.take());
-
-        for worker in &mut self.workers {
-            println!("Shutting down worker {}", worker.id);
-
-            // the take method takes out the Some variant and leaves None in its place
-            // None in this case means the worker has already had its thread cleaned up and no active thread
-            if let Some(thread) = worker.thread.take() {
-                thread.join().unwrap();
-            }
+        // after shutdown there's nothing left to do, and waiting on a job it gave up on could take forever
+        if self.queue.lock().closed {
+            return;
         }
+        // without an explicit shutdown, let every queued job run, however long that takes
+        self.stop(ShutdownMode::Drain, None);
     }
 }
 
@@ -41,47 +50,536 @@ impl ThreadPool {
     pub fn new(size: usize) -> ThreadPool {
         assert!(size > 0); // defining the size param as usize eliminates the possibility of it being negative, but it could still be zero, which is valid but doesn't make sense in this case so test it!
 
-        let (sender, receiver) = mpsc::channel();
+        ThreadPool::builder(size).build().unwrap()
+    }
+    /// Start configuring a pool of `size` threads, e.g. to limit how many jobs may queue up.
+    pub fn builder(size: usize) -> ThreadPoolBuilder {
+        ThreadPoolBuilder {
+            size,
+            max_size: None,
+            keep_alive: Duration::from_secs(60),
+            queue_capacity: None,
+            queue_policy: QueuePolicy::default(),
+        }
+    }
+    /// Queue `f` to be run by one of the workers.
+    ///
+    /// If the queue is full, the pool's [`QueuePolicy`] decides what happens;
+    /// only [`QueuePolicy::Reject`] makes this return an error.
+    pub fn execute<F>(&self, f: F) -> Result<(), ExecuteError>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let job: Job = Box::new(f);
+        let mut dropped = None;
 
-        let receiver = Arc::new(Mutex::new(receiver));
+        let mut queue = self.queue.lock();
+        if let Some(capacity) = self.queue_capacity {
+            while queue.jobs.len() >= capacity {
+                match self.queue_policy {
+                    QueuePolicy::Block => queue = self.queue.wait(&self.queue.job_taken, queue),
+                    QueuePolicy::Reject => return Err(ExecuteError::QueueFull),
+                    QueuePolicy::DropOldest => dropped = queue.jobs.pop_front(),
+                    QueuePolicy::CallerRuns => {
+                        // let the workers get on with the queue in the meantime
+                        drop(queue);
+                        if let Err(e) = run_job(job, &self.panics) {
+                            eprintln!("The caller caught a panicking job: {}", e.message());
+                        }
+                        return Ok(());
+                    }
+                }
+            }
+        }
+        queue.jobs.push_back(job);
+        self.queue.job_added.notify_one();
 
-        // Vec::with_capacity is similar to Vec::new but it preallocates space in the vec
-        let mut workers = Vec::with_capacity(size); // you know you need to store size elements in the vec in this case, so it's more efficient
+        // more jobs waiting than idle workers to take them: bring in another worker, if the pool may grow
+        let new_worker = if queue.jobs.len() > queue.idle && queue.live < queue.max {
+            Some(queue.enlist())
+        } else {
+            None
+        };
+        drop(queue);
 
-        for id in 0..size {
-            workers.push(Worker::new(id, Arc::clone(&receiver)));
-        }
-        ThreadPool {
-            workers,
-            sender: Some(sender),
+        if let Some(id) = new_worker {
+            self.add_worker(id);
         }
+        // dropping a job can run any code its closure captured, so not while the queue is locked
+        drop(dropped);
+        Ok(())
     }
-    pub fn execute<F>(&self, f: F)
+    /// Run `f` on the pool and get back a handle to its result.
+    ///
+    /// Unlike `execute`, a panic in `f` doesn't get lost: it's caught and handed to whoever joins the handle.
+    pub fn spawn<F, T>(&self, f: F) -> Result<JobHandle<T>, ExecuteError>
     where
-        F: FnOnce() + Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
     {
-        let job = Box::new(f);
+        // a one-slot channel is all it takes to carry a single result back, and the job never has to wait on it
+        let (sender, receiver) = mpsc::sync_channel(1);
+        let panics = Arc::clone(&self.panics);
+
+        self.execute(move || {
+            // caught in here, so the panic goes to the handle rather than to the worker (which would only print it)
+            let result = run_job(f, &panics);
+            // nobody's listening if the handle was dropped, which is fine: the job was fire-and-forget after all
+            let _ = sender.send(result);
+        })?;
 
-        self.sender.as_ref().unwrap().send(job).unwrap();
+        Ok(JobHandle { receiver })
+    }
+    /// Change the pool's (minimum) size to `size` while it's running.
+    ///
+    /// Missing workers start right away, and ones beyond the new size stop as soon as they're done with their current job.
+    /// A pool built with a `max_size` keeps the same room to grow on top of the new size.
+    ///
+    /// # Panics
+    ///
+    /// The `resize` function will panic if the size is zero.
+    pub fn resize(&self, size: usize) {
+        assert!(size > 0);
+
+        let mut queue = self.queue.lock();
+        let room_to_grow = queue.max - queue.min;
+        queue.min = size;
+        queue.max = size + room_to_grow;
+        let missing = size.saturating_sub(queue.live);
+        let ids: Vec<usize> = (0..missing).map(|_| queue.enlist()).collect();
+        drop(queue);
+
+        // idle workers beyond the new maximum only notice once they wake up
+        self.queue.job_added.notify_all();
+        for id in ids {
+            self.add_worker(id);
+        }
+    }
+    /// How many workers the pool has right now.
+    pub fn worker_count(&self) -> usize {
+        self.queue.lock().live
+    }
+    // starts the worker that Queue::enlist made room for
+    fn add_worker(&self, id: usize) {
+        let mut workers = self.workers.lock().unwrap_or_else(PoisonError::into_inner);
+        // retired workers' threads have already finished, so there's nothing left to join
+        workers.retain(|worker| !worker.thread.as_ref().is_some_and(|t| t.is_finished()));
+        workers.push(Worker::new(
+            id,
+            Arc::clone(&self.queue),
+            Arc::clone(&self.panics),
+        ));
+    }
+    /// Stop the pool, waiting at most `timeout` for its jobs to finish.
+    ///
+    /// [`ShutdownMode::Drain`] lets the queued jobs run first, [`ShutdownMode::Discard`] throws them away.
+    /// Queued jobs that haven't started when the time is up are thrown away too, and workers still busy
+    /// with a job are left to finish it in the background rather than waited for.
+    pub fn shutdown(mut self, mode: ShutdownMode, timeout: Duration) -> ShutdownReport {
+        self.stop(mode, Some(timeout))
+    }
+    // the shared part of shutdown and drop; no timeout waits for however long the jobs take
+    fn stop(&mut self, mode: ShutdownMode, timeout: Option<Duration>) -> ShutdownReport {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        // close the queue so no more jobs will be added; the workers finish what's in it (if anything) and then stop
+        let mut queue = self.queue.lock();
+        queue.closed = true;
+        self.queue.job_added.notify_all();
+
+        let mut cancelled: Vec<Job> = Vec::new();
+        if mode == ShutdownMode::Discard {
+            cancelled.extend(queue.jobs.drain(..));
+        }
+        while !queue.jobs.is_empty() || queue.running > 0 {
+            queue = match deadline {
+                None => self.queue.wait(&self.queue.job_done, queue),
+                Some(deadline) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        break;
+                    }
+                    self.queue
+                        .job_done
+                        .wait_timeout(queue, left)
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0
+                }
+            };
+        }
+        // out of time: whatever hasn't started by now never will
+        cancelled.extend(queue.jobs.drain(..));
+
+        let report = ShutdownReport {
+            completed: queue.completed,
+            cancelled: cancelled.len(),
+            still_running: queue.running,
+        };
+        drop(queue);
+        // dropping a job can run any code its closure captured, so not while the queue is locked
+        drop(cancelled);
+
+        let workers = mem::take(
+            self.workers
+                .get_mut()
+                .unwrap_or_else(PoisonError::into_inner),
+        );
+        // a stuck job would keep its worker from ever being joined, so with one around they're all left to end on their own
+        if report.still_running == 0 {
+            for mut worker in workers {
+                // the take method takes out the Some variant and leaves None in its place
+                // None in this case means the worker has already had its thread cleaned up and no active thread
+                if let Some(thread) = worker.thread.take() {
+                    thread.join().unwrap();
+                }
+            }
+        }
+
+        report
+    }
+    /// How many jobs passed to `execute` or `spawn` have panicked so far.
+    ///
+    /// A panicking job doesn't take its worker down with it, so the pool keeps its size no matter how many there were.
+    pub fn panic_count(&self) -> usize {
+        self.panics.load(Ordering::Relaxed)
     }
     pub fn build(size: usize) -> Result<ThreadPool, &'static str /* PoolCreationError */> {
-        if size > 0 {
-            let (sender, receiver) = mpsc::channel();
-            let receiver = Arc::new(Mutex::new(receiver));
-            let mut workers = Vec::with_capacity(size);
-            for id in 0..size {
-                workers.push(Worker::new(id, Arc::clone(&receiver)));
+        ThreadPool::builder(size).build()
+    }
+}
+
+/// What [`ThreadPool::execute`] does with a job when the queue is already full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QueuePolicy {
+    /// Wait until a worker takes a job off the queue and there's room again.
+    #[default]
+    Block,
+    /// Don't queue the job, and return [`ExecuteError::QueueFull`] instead.
+    Reject,
+    /// Throw away the job that's been waiting the longest to make room.
+    DropOldest,
+    /// Run the job right away on the thread that called `execute`, which slows down whoever's adding jobs.
+    CallerRuns,
+}
+
+/// Why [`ThreadPool::execute`] didn't take a job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecuteError {
+    /// The queue was full and the pool's policy is [`QueuePolicy::Reject`].
+    QueueFull,
+}
+
+impl fmt::Display for ExecuteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecuteError::QueueFull => write!(f, "the job queue is full"),
+        }
+    }
+}
+
+impl Error for ExecuteError {}
+
+/// Whether [`ThreadPool::shutdown`] runs the jobs still in the queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownMode {
+    /// Run every queued job before stopping (as long as the timeout allows).
+    Drain,
+    /// Throw the queued jobs away; only the ones already running get to finish.
+    Discard,
+}
+
+/// How the jobs stood when [`ThreadPool::shutdown`] returned.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// Jobs the workers ran to the end (or until they panicked) over the pool's whole life.
+    pub completed: usize,
+    /// Queued jobs that were thrown away without running.
+    pub cancelled: usize,
+    /// Jobs that were still running when the timeout ran out.
+    pub still_running: usize,
+}
+
+/// Configures a [`ThreadPool`] before starting it; see [`ThreadPool::builder`].
+#[derive(Debug, Clone)]
+pub struct ThreadPoolBuilder {
+    size: usize,
+    max_size: Option<usize>,
+    keep_alive: Duration,
+    queue_capacity: Option<usize>,
+    queue_policy: QueuePolicy,
+}
+
+impl ThreadPoolBuilder {
+    /// Let the pool grow up to `max_size` workers while jobs are waiting for one.
+    ///
+    /// The workers beyond its size stop again once they've been idle for the keep-alive.
+    pub fn max_size(mut self, max_size: usize) -> ThreadPoolBuilder {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// How long a worker beyond the pool's size waits for another job before it stops, a minute by default.
+    pub fn keep_alive(mut self, keep_alive: Duration) -> ThreadPoolBuilder {
+        self.keep_alive = keep_alive;
+        self
+    }
+
+    /// Let at most `capacity` jobs wait for a worker; by default the queue grows as needed.
+    pub fn queue_capacity(mut self, capacity: usize) -> ThreadPoolBuilder {
+        self.queue_capacity = Some(capacity);
+        self
+    }
+
+    /// What to do with a job when the queue is full, [`QueuePolicy::Block`] by default.
+    pub fn queue_policy(mut self, policy: QueuePolicy) -> ThreadPoolBuilder {
+        self.queue_policy = policy;
+        self
+    }
+
+    pub fn build(self) -> Result<ThreadPool, &'static str> {
+        if self.size == 0 {
+            return Err("Something went wrong when creating a thread pool.");
+        }
+        // nothing could ever be queued, and Block would wait forever
+        if self.queue_capacity == Some(0) {
+            return Err("The job queue needs room for at least one job.");
+        }
+        let max_size = self.max_size.unwrap_or(self.size);
+        if max_size < self.size {
+            return Err("The maximum size can't be less than the size.");
+        }
+
+        let queue = Arc::new(Queue::new(self.size, max_size, self.keep_alive));
+        let panics = Arc::new(AtomicUsize::new(0));
+
+        // Vec::with_capacity is similar to Vec::new but it preallocates space in the vec
+        let mut workers = Vec::with_capacity(self.size); // you know you need to store size elements in the vec in this case, so it's more efficient
+
+        for id in 0..self.size {
+            workers.push(Worker::new(id, Arc::clone(&queue), Arc::clone(&panics)));
+        }
+        Ok(ThreadPool {
+            workers: Mutex::new(workers),
+            queue,
+            queue_capacity: self.queue_capacity,
+            queue_policy: self.queue_policy,
+            panics,
+        })
+    }
+}
+
+// the jobs waiting for a worker
+// a channel can't say how many jobs are waiting, let alone give up the oldest one, which the queue policies need
+struct Queue {
+    state: Mutex<QueueState>,
+    // signalled when a job is added or the queue is closed, for idle workers
+    job_added: Condvar,
+    // signalled when a worker takes a job, for execute waiting on a full queue
+    job_taken: Condvar,
+    // signalled when a worker finishes a job, for shutdown
+    job_done: Condvar,
+    // how long a worker beyond min waits for a job before it retires
+    keep_alive: Duration,
+}
+
+// the workers are counted under the same lock as the jobs, so whether to add or retire one is decided
+// with the queue's length in view and can't race with another worker making the same call
+struct QueueState {
+    jobs: VecDeque<Job>,
+    closed: bool,
+    // how many workers the pool keeps at least, and may grow to while jobs are waiting
+    min: usize,
+    max: usize,
+    // workers that are running (or about to), and how many of those are waiting for a job
+    live: usize,
+    idle: usize,
+    next_id: usize,
+    // jobs being run right now, and ones run to the end so far
+    running: usize,
+    completed: usize,
+}
+
+impl QueueState {
+    // counts in a worker that's about to be started, and returns its id
+    fn enlist(&mut self) -> usize {
+        self.live += 1;
+        self.next_id += 1;
+        self.next_id - 1
+    }
+}
+
+impl Queue {
+    fn new(min: usize, max: usize, keep_alive: Duration) -> Queue {
+        Queue {
+            state: Mutex::new(QueueState {
+                jobs: VecDeque::new(),
+                closed: false,
+                min,
+                max,
+                live: min,
+                idle: 0,
+                next_id: min,
+                running: 0,
+                completed: 0,
+            }),
+            job_added: Condvar::new(),
+            job_taken: Condvar::new(),
+            job_done: Condvar::new(),
+            keep_alive,
+        }
+    }
+
+    // the lock is never held while a job runs, so a poisoned mutex (should something panic while holding it anyway)
+    // still guards a perfectly good queue: carry on with it
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn wait<'a>(
+        &self,
+        condvar: &Condvar,
+        state: MutexGuard<'a, QueueState>,
+    ) -> MutexGuard<'a, QueueState> {
+        condvar.wait(state).unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // waits for the next job; None means the worker asking should stop, which it's already been counted out for:
+    // the pool was resized below it, it's been idle for keep_alive in a pool that's grown, or the queue was closed and is empty
+    fn pop(&self) -> Option<Job> {
+        let mut state = self.lock();
+        let mut timed_out = false;
+        loop {
+            if state.live > state.max {
+                state.live -= 1;
+                return None;
             }
-            Ok(ThreadPool {
-                workers,
-                sender: Some(sender),
-            })
-        } else {
-            Err("Something went wrong when creating a thread pool.")
+            if let Some(job) = state.jobs.pop_front() {
+                state.running += 1;
+                self.job_taken.notify_one();
+                return Some(job);
+            }
+            if state.closed || (timed_out && state.live > state.min) {
+                state.live -= 1;
+                return None;
+            }
+
+            state.idle += 1;
+            let (guard, result) = self
+                .job_added
+                .wait_timeout(state, self.keep_alive)
+                .unwrap_or_else(PoisonError::into_inner);
+            state = guard;
+            state.idle -= 1;
+            timed_out = result.timed_out();
+        }
+    }
+
+    // a worker calls this once it's done with a job pop handed it
+    fn finish(&self) {
+        let mut state = self.lock();
+        state.running -= 1;
+        state.completed += 1;
+        self.job_done.notify_all();
+    }
+}
+
+// runs a job, catching a panic so it can't take the thread running it down with it
+fn run_job<T>(job: impl FnOnce() -> T, panics: &AtomicUsize) -> Result<T, JobPanicked> {
+    panic::catch_unwind(AssertUnwindSafe(job)).map_err(|payload| {
+        panics.fetch_add(1, Ordering::Relaxed);
+        JobPanicked::from_payload(payload)
+    })
+}
+
+/// A handle to a job started with [`ThreadPool::spawn`], for getting its result.
+///
+/// Dropping the handle doesn't cancel the job; its result is just thrown away.
+pub struct JobHandle<T> {
+    receiver: mpsc::Receiver<Result<T, JobPanicked>>,
+}
+
+impl<T> JobHandle<T> {
+    /// Wait for the job to finish and return what it returned, or why it didn't.
+    pub fn join(self) -> Result<T, JobError> {
+        // the sender only goes away without sending if the job was dropped before it could run
+        match self.receiver.recv() {
+            Ok(result) => result.map_err(JobError::Panicked),
+            Err(_) => Err(JobError::Cancelled),
+        }
+    }
+
+    /// Return the job's result if it has finished, without waiting.
+    ///
+    /// If it hasn't, the handle comes back as the `Err` so you can try again later (or `join` it).
+    pub fn try_join(self) -> Result<Result<T, JobError>, JobHandle<T>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Ok(result.map_err(JobError::Panicked)),
+            Err(mpsc::TryRecvError::Empty) => Err(self),
+            Err(mpsc::TryRecvError::Disconnected) => Ok(Err(JobError::Cancelled)),
         }
     }
 }
 
+/// Why a [`JobHandle`] has no result to give back.
+#[derive(Debug)]
+pub enum JobError {
+    /// The job panicked instead of returning.
+    Panicked(JobPanicked),
+    /// The job never ran: [`QueuePolicy::DropOldest`] or [`ThreadPool::shutdown`] threw it away first.
+    Cancelled,
+}
+
+impl fmt::Display for JobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobError::Panicked(e) => write!(f, "{e}"),
+            JobError::Cancelled => write!(f, "the job was cancelled before it ran"),
+        }
+    }
+}
+
+impl Error for JobError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            JobError::Panicked(e) => Some(e),
+            JobError::Cancelled => None,
+        }
+    }
+}
+
+/// How a job panicked, see [`JobError::Panicked`].
+#[derive(Debug)]
+pub struct JobPanicked {
+    message: String,
+}
+
+impl JobPanicked {
+    // panic! with a literal gives a &str payload and with format args a String; anything else came from panic_any
+    fn from_payload(payload: Box<dyn Any + Send>) -> JobPanicked {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => match payload.downcast::<&'static str>() {
+                Ok(message) => message.to_string(),
+                Err(_) => "Box<dyn Any>".to_string(),
+            },
+        };
+        JobPanicked { message }
+    }
+
+    /// What the job panicked with.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for JobPanicked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "job panicked: {}", self.message)
+    }
+}
+
+impl Error for JobPanicked {}
+
 // like kitchen staff members in a restaurant, wait until the customers finalize their orders, and then fulfill them
 struct Worker {
     id: usize,
@@ -89,17 +587,21 @@ struct Worker {
 }
 
 impl Worker {
-    fn new(id: usize, receiver: Arc<Mutex<mpsc::Receiver<Job>>>) -> Worker {
+    fn new(id: usize, queue: Arc<Queue>, panics: Arc<AtomicUsize>) -> Worker {
         let thread: thread::JoinHandle<()> = thread::spawn(move || loop {
             /*  A graceful exit pattern */
-            let message = receiver.lock().unwrap().recv();
+            let message = queue.pop();
             match message {
-                Ok(job) => {
+                Some(job) => {
                     println!("Worker {id} got a job; executing.");
 
-                    job();
+                    // a panicking job would otherwise end this thread, and the pool would be a worker short for good
+                    if let Err(e) = run_job(job, &panics) {
+                        eprintln!("Worker {id} caught a panicking job: {}", e.message());
+                    }
+                    queue.finish();
                 }
-                Err(_) => {
+                None => {
                     println!("Worker {id} disconnected; shutting down.");
                     break;
                 }
@@ -132,3 +634,259 @@ impl Worker {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn spawned_jobs_hand_back_their_results() {
+        let pool = ThreadPool::new(2);
+
+        let handles: Vec<_> = (0..8).map(|i| pool.spawn(move || i * i).unwrap()).collect();
+        let results: Vec<i32> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+        assert_eq!(vec![0, 1, 4, 9, 16, 25, 36, 49], results);
+    }
+
+    #[test]
+    fn a_panicking_job_is_reported_to_its_handle() {
+        let pool = ThreadPool::new(1);
+
+        let handle = pool.spawn(|| -> i32 { panic!("boom {}", 42) }).unwrap();
+        assert!(matches!(handle.join(), Err(JobError::Panicked(e)) if e.message() == "boom 42"));
+
+        // the panic was caught, so the only worker is still around for the next job
+        assert_eq!("fine", pool.spawn(|| "fine").unwrap().join().unwrap());
+        assert_eq!(1, pool.panic_count());
+    }
+
+    #[test]
+    fn a_poisoned_queue_lock_is_carried_on_with() {
+        let pool = ThreadPool::new(1);
+
+        thread::scope(|scope| {
+            let poisoner = scope.spawn(|| {
+                let _state = pool.queue.lock();
+                panic!("poisoning the queue");
+            });
+            assert!(poisoner.join().is_err());
+        });
+        assert!(pool.queue.state.is_poisoned());
+
+        assert_eq!(5, pool.spawn(|| 5).unwrap().join().unwrap());
+        pool.resize(2);
+        assert_eq!(2, pool.worker_count());
+    }
+
+    #[test]
+    fn panicking_jobs_do_not_shrink_the_pool() {
+        // one worker runs everything in order, so it has to have survived every panic to get to the spawned jobs
+        let pool = ThreadPool::new(1);
+
+        for _ in 0..4 {
+            pool.execute(|| panic!("boom")).unwrap();
+        }
+        let handles: Vec<_> = (0..4).map(|i| pool.spawn(move || i).unwrap()).collect();
+        let total: i32 = handles.into_iter().map(|h| h.join().unwrap()).sum();
+
+        assert_eq!(6, total);
+        assert_eq!(4, pool.panic_count());
+    }
+
+    #[test]
+    fn try_join_does_not_wait() {
+        let pool = ThreadPool::new(1);
+        let (release, wait) = mpsc::channel::<()>();
+
+        let handle = pool.spawn(move || wait.recv().is_ok()).unwrap();
+        let mut handle = handle.try_join().expect_err("job can't have finished yet");
+
+        release.send(()).unwrap();
+        loop {
+            match handle.try_join() {
+                Ok(result) => break assert!(result.unwrap()),
+                Err(pending) => handle = pending,
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    // a pool of one worker that's busy until the returned sender is dropped, so whatever comes next has to queue
+    fn busy_pool(capacity: usize, policy: QueuePolicy) -> (ThreadPool, mpsc::Sender<()>) {
+        let pool = ThreadPool::builder(1)
+            .queue_capacity(capacity)
+            .queue_policy(policy)
+            .build()
+            .unwrap();
+        let (started, has_started) = mpsc::channel();
+        let (release, wait) = mpsc::channel::<()>();
+
+        pool.execute(move || {
+            started.send(()).unwrap();
+            let _ = wait.recv();
+        })
+        .unwrap();
+        has_started.recv().unwrap();
+
+        (pool, release)
+    }
+
+    #[test]
+    fn full_queues_reject_or_drop_the_oldest_job() {
+        let (pool, release) = busy_pool(1, QueuePolicy::Reject);
+        let queued = pool.spawn(|| 1).unwrap();
+        assert_eq!(Some(ExecuteError::QueueFull), pool.spawn(|| 2).err());
+        drop(release);
+        assert_eq!(1, queued.join().unwrap());
+
+        let (pool, release) = busy_pool(1, QueuePolicy::DropOldest);
+        let oldest = pool.spawn(|| 1).unwrap();
+        let newest = pool.spawn(|| 2).unwrap();
+        drop(release);
+        assert!(matches!(oldest.join(), Err(JobError::Cancelled)));
+        assert_eq!(2, newest.join().unwrap());
+    }
+
+    #[test]
+    fn full_queues_block_or_run_on_the_caller() {
+        let (pool, release) = busy_pool(1, QueuePolicy::CallerRuns);
+        pool.spawn(|| ()).unwrap();
+        let caller = thread::current().id();
+        let ran_on = pool.spawn(move || thread::current().id()).unwrap();
+        // it's already done by the time spawn returns
+        assert!(matches!(ran_on.try_join(), Ok(Ok(id)) if id == caller));
+        drop(release);
+
+        let (pool, release) = busy_pool(1, QueuePolicy::Block);
+        pool.spawn(|| ()).unwrap();
+        thread::scope(|scope| {
+            let blocked = scope.spawn(|| pool.spawn(|| 3).unwrap().join().unwrap());
+            thread::sleep(Duration::from_millis(50));
+            assert!(!blocked.is_finished());
+
+            drop(release);
+            assert_eq!(3, blocked.join().unwrap());
+        });
+    }
+
+    // waits until the pool has count workers, or fails after a few seconds
+    fn wait_for_workers(pool: &ThreadPool, count: usize) {
+        for _ in 0..300 {
+            if pool.worker_count() == count {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(count, pool.worker_count());
+    }
+
+    // runs count jobs that only finish once the returned sender is dropped, and checks they all got going at once
+    fn run_side_by_side(pool: &ThreadPool, count: usize) -> mpsc::Sender<()> {
+        let (started, has_started) = mpsc::channel();
+        let (release, wait) = mpsc::channel::<()>();
+        let wait = Arc::new(Mutex::new(wait));
+
+        for _ in 0..count {
+            let started = started.clone();
+            let wait = Arc::clone(&wait);
+            pool.execute(move || {
+                started.send(()).unwrap();
+                let _ = wait.lock().unwrap().recv();
+            })
+            .unwrap();
+        }
+        for _ in 0..count {
+            has_started
+                .recv_timeout(Duration::from_secs(3))
+                .expect("not enough workers to run the jobs side by side");
+        }
+
+        release
+    }
+
+    #[test]
+    fn elastic_pools_grow_while_jobs_wait_and_shrink_when_idle() {
+        let pool = ThreadPool::builder(1)
+            .max_size(3)
+            .keep_alive(Duration::from_millis(20))
+            .build()
+            .unwrap();
+
+        drop(run_side_by_side(&pool, 3));
+        wait_for_workers(&pool, 1);
+    }
+
+    #[test]
+    fn resizing_a_running_pool() {
+        let pool = ThreadPool::new(1);
+
+        pool.resize(3);
+        assert_eq!(3, pool.worker_count());
+        drop(run_side_by_side(&pool, 3));
+
+        pool.resize(1);
+        wait_for_workers(&pool, 1);
+        assert_eq!(6, pool.spawn(|| 6).unwrap().join().unwrap());
+    }
+
+    #[test]
+    fn shutdown_drains_or_discards_the_queue() {
+        let (pool, release) = busy_pool(1, QueuePolicy::Block);
+        let queued = pool.spawn(|| 1).unwrap();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            drop(release);
+        });
+        let report = pool.shutdown(ShutdownMode::Drain, Duration::from_secs(3));
+        assert_eq!(
+            ShutdownReport {
+                completed: 2,
+                cancelled: 0,
+                still_running: 0
+            },
+            report
+        );
+        assert_eq!(1, queued.join().unwrap());
+
+        let (pool, release) = busy_pool(2, QueuePolicy::Block);
+        let queued: Vec<_> = (0..2).map(|i| pool.spawn(move || i).unwrap()).collect();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            drop(release);
+        });
+        let report = pool.shutdown(ShutdownMode::Discard, Duration::from_secs(3));
+        assert_eq!(
+            ShutdownReport {
+                completed: 1,
+                cancelled: 2,
+                still_running: 0
+            },
+            report
+        );
+        assert!(queued
+            .into_iter()
+            .all(|handle| matches!(handle.join(), Err(JobError::Cancelled))));
+    }
+
+    #[test]
+    fn shutdown_gives_up_on_stuck_jobs() {
+        let (pool, release) = busy_pool(1, QueuePolicy::Block);
+        pool.spawn(|| ()).unwrap();
+
+        let started = Instant::now();
+        let report = pool.shutdown(ShutdownMode::Drain, Duration::from_millis(50));
+
+        assert!(started.elapsed() < Duration::from_secs(3));
+        assert_eq!(
+            ShutdownReport {
+                completed: 0,
+                cancelled: 1,
+                still_running: 1
+            },
+            report
+        );
+        drop(release);
+    }
+}
@@ -1,4 +1,4 @@
-use hello::ThreadPool;
+use hello::{ShutdownMode, ThreadPool};
 use std::{
     fs,
     io::prelude::*,
@@ -9,18 +9,29 @@ use std::{
 
 fn main() {
     let listener = TcpListener::bind("127.0.0.1:7878").unwrap();
-    let pool = ThreadPool::new(4);
+    // a burst of connections waits in the accept loop once 16 are queued, rather than piling up in memory
+    let pool = ThreadPool::builder(4).queue_capacity(16).build().unwrap();
 
     // add the take method with the limit num of requests as its arg so a graceful shutdown can be fulfilled when the num of requests reaches it
     for stream in listener.incoming().take(2) {
         let stream = stream.unwrap();
 
-        pool.execute(|| {
+        if let Err(e) = pool.execute(|| {
             handle_connection(stream);
-        });
+        }) {
+            eprintln!("Couldn't handle a connection: {e}");
+        }
     }
 
     println!("Shutting down...");
+    // give the requests still being handled a while to finish, but don't hang on one that never does
+    let report = pool.shutdown(ShutdownMode::Drain, Duration::from_secs(10));
+    if report.still_running > 0 {
+        eprintln!(
+            "Gave up waiting on {} request(s) still being handled",
+            report.still_running
+        );
+    }
     // let listener: TcpListener = TcpListener::bind("127.0.0.1:7878").unwrap();
 
     // for stream in listener.incoming() {
@@ -33,7 +44,9 @@ fn main() {
 fn handle_connection(mut stream: TcpStream) {
     /* Multi-threaded server pattern with a thread pool */
     let mut buffer = [0; 1024];
-    stream.read(&mut buffer).unwrap();
+    // only the bytes actually read are the request; the rest of the buffer is just zeros
+    let read = stream.read(&mut buffer).unwrap();
+    let buffer = &buffer[..read];
 
     let get = b"GET / HTTP/1.1\r\n";
     let sleep = b"GET /sleep HTTP/1.1\r\n";
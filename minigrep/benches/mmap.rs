@@ -0,0 +1,88 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use minigrep::{Command, Config};
+
+// 1 GiB by default; set MINIGREP_BENCH_BYTES for a quicker run, e.g. MINIGREP_BENCH_BYTES=100000000
+fn corpus_size() -> u64 {
+    env::var("MINIGREP_BENCH_BYTES")
+        .ok()
+        .and_then(|bytes| bytes.parse().ok())
+        .unwrap_or(1 << 30)
+}
+
+// one big log-like file with a needle every few thousand lines
+// it's kept between runs since writing a gigabyte takes a while
+fn corpus(size: u64) -> PathBuf {
+    let path = env::temp_dir().join(format!("minigrep-bench-mmap-{size}.txt"));
+    if fs::metadata(&path).is_ok_and(|meta| meta.len() >= size) {
+        return path;
+    }
+
+    let mut out = BufWriter::new(File::create(&path).unwrap());
+    let mut written = 0;
+    let mut line = 0u64;
+    while written < size {
+        let text = if line.is_multiple_of(4099) {
+            format!(
+                "{line:>10} ERROR worker {} found the needle in the haystack\n",
+                line % 17
+            )
+        } else {
+            format!(
+                "{line:>10} INFO worker {} handled request in {}ms\n",
+                line % 17,
+                line % 250
+            )
+        };
+        out.write_all(text.as_bytes()).unwrap();
+        written += text.len() as u64;
+        line += 1;
+    }
+    out.flush().unwrap();
+
+    path
+}
+
+fn mmap_vs_search(c: &mut Criterion) {
+    let size = corpus_size();
+    let path = corpus(size);
+    let mut group = c.benchmark_group(format!("{size} bytes"));
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(size));
+
+    // the book's search: the whole file in a String, scanned a line at a time
+    // the read happens once up front, so only the scan itself is measured
+    let contents = fs::read_to_string(&path).unwrap();
+    group.bench_function("search", |b| {
+        b.iter(|| minigrep::search("needle", &contents).len())
+    });
+    drop(contents);
+
+    // run reads the file itself, either mapped or through a BufReader
+    for (name, extra) in [
+        ("run_to mmap", None),
+        ("run_to buffered", Some("--no-mmap")),
+    ] {
+        let args = ["minigrep", "-c", "needle"]
+            .into_iter()
+            .chain(extra)
+            .map(String::from)
+            .chain([path.display().to_string()]);
+        let Command::Run(config) = Config::build(args).unwrap() else {
+            unreachable!("the args don't ask for --help or --version")
+        };
+
+        group.bench_function(name, |b| {
+            b.iter(|| minigrep::run_to(&config, io::sink()).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, mmap_vs_search);
+criterion_main!(benches);
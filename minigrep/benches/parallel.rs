@@ -0,0 +1,59 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use minigrep::{Command, Config};
+
+// a tree of a few hundred medium-sized files, roughly the shape of a source checkout
+fn corpus() -> PathBuf {
+    let root = env::temp_dir().join("minigrep-bench-parallel");
+    if root.exists() {
+        return root;
+    }
+
+    for dir in 0..16 {
+        let dir_path = root.join(format!("dir{dir:02}"));
+        fs::create_dir_all(&dir_path).unwrap();
+        for file in 0..25 {
+            let mut contents = String::new();
+            for line in 0..4_000 {
+                if line % 997 == 0 {
+                    contents.push_str("let needle = find_the_needle(haystack);\n");
+                } else {
+                    contents
+                        .push_str("fn filler(x: usize) -> usize { x * 2 + 1 } // nothing to see\n");
+                }
+            }
+            fs::write(dir_path.join(format!("file{file:02}.rs")), contents).unwrap();
+        }
+    }
+
+    root
+}
+
+fn parallel_vs_sequential(c: &mut Criterion) {
+    let root = corpus();
+    let mut group = c.benchmark_group("run_to");
+    group.sample_size(10);
+
+    for jobs in [1, 2, 4, 8] {
+        let args = ["minigrep", "-n", "-j", &jobs.to_string(), "needle"]
+            .map(String::from)
+            .into_iter()
+            .chain([root.display().to_string()]);
+        let Command::Run(config) = Config::build(args).unwrap() else {
+            unreachable!("the args don't ask for --help or --version")
+        };
+
+        group.bench_with_input(BenchmarkId::new("jobs", jobs), &config, |b, config| {
+            b.iter(|| minigrep::run_to(config, io::sink()).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, parallel_vs_sequential);
+criterion_main!(benches);
@@ -0,0 +1,70 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use minigrep::{Command, Config};
+
+// a log of about 1.4 MB, where one line in a hundred mentions a user by name
+fn corpus() -> PathBuf {
+    let path = env::temp_dir().join("minigrep-bench-patterns.txt");
+    if path.exists() {
+        return path;
+    }
+
+    let mut contents = String::new();
+    for line in 0..35_000 {
+        if line % 100 == 0 {
+            contents.push_str(&format!(
+                "{line:>8} Login from User{} accepted\n",
+                line % 3000
+            ));
+        } else {
+            contents.push_str(&format!(
+                "{line:>8} INFO handled request in {}ms\n",
+                line % 250
+            ));
+        }
+    }
+    fs::write(&path, contents).unwrap();
+
+    path
+}
+
+// a -f file of count user names, the way a list of people to look out for would be kept
+fn pattern_file(count: usize) -> PathBuf {
+    let path = env::temp_dir().join(format!("minigrep-bench-patterns-{count}.pat"));
+    let names: String = (0..count).map(|i| format!("user{i}\n")).collect();
+    fs::write(&path, names).unwrap();
+    path
+}
+
+fn pattern_sets(c: &mut Criterion) {
+    let path = corpus();
+    let mut group = c.benchmark_group("pattern sets");
+    group.sample_size(10);
+
+    for count in [200, 2000] {
+        let patterns = pattern_file(count);
+        // with -i, Aho–Corasick runs over each line folded; without, over the line as it is
+        for case in ["-s", "-i"] {
+            let args = ["minigrep", "-c", case, "-f"]
+                .map(String::from)
+                .into_iter()
+                .chain([patterns.display().to_string(), path.display().to_string()]);
+            let Command::Run(config) = Config::build(args).unwrap() else {
+                unreachable!("the args don't ask for --help or --version")
+            };
+
+            group.bench_with_input(BenchmarkId::new(case, count), &config, |b, config| {
+                b.iter(|| minigrep::run_to(config, io::sink()).unwrap())
+            });
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, pattern_sets);
+criterion_main!(benches);
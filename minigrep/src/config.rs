@@ -0,0 +1,948 @@
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal};
+
+use encoding_rs::Encoding;
+use ignore::overrides::OverrideBuilder;
+use ignore::types::TypesBuilder;
+use regex::Regex;
+
+use crate::{CaseInsensitiveMatcher, FuzzyMatcher, LiteralMatcher, Matcher, MultiLiteralMatcher};
+use crate::{MinigrepError, OutputMode, PrintOptions, WalkOptions};
+use crate::{RegexMatcher, WordMatcher};
+
+pub struct Config {
+    // the query, or every -e and every line of every -f, in the order they were given
+    pub patterns: Vec<String>,
+    // files and/or directories; directories are searched recursively
+    pub file_paths: Vec<String>,
+    pub ignore_case: bool,
+    // Some in --regex mode
+    // compiled once up front so an invalid pattern is reported before any file is read
+    pub regex: Option<RegexMatcher>,
+    // Some for several literal patterns matched case-sensitively
+    pub literals: Option<MultiLiteralMatcher>,
+    // and for several of them with -i
+    pub folded_literals: Option<CaseInsensitiveMatcher>,
+    // -w: only matches that are whole words
+    pub word_regexp: bool,
+    // --fuzzy: how many edits away from the query a match may be; None matches it exactly
+    pub fuzzy: Option<usize>,
+    // -n, -A, -B and -C
+    pub print_options: PrintOptions,
+    // -v: select the lines that don't match
+    pub invert_match: bool,
+    // -c, -l, -L and --json; plain lines otherwise
+    pub output_mode: OutputMode,
+    // --color
+    pub color: ColorChoice,
+    // -j: how many files to search at once; 1 searches them one after another on the calling thread
+    pub jobs: usize,
+    // --hidden, --no-ignore, --glob, --exclude and --type: which files in a directory get searched
+    pub walk_options: WalkOptions,
+    // --replace: rewrite the matches with this instead of printing them, showing a diff of the result
+    pub replace: Option<String>,
+    // --in-place: write the replacements back to the files instead of showing the diff
+    pub in_place: bool,
+    // -z: decompress every input, even ones that don't look compressed
+    pub decompress: bool,
+    // --encoding: what text without a BOM is encoded in; None leaves it as is, to be searched as UTF-8
+    pub encoding: Option<&'static Encoding>,
+    // --text: search binary files as if they were text instead of just saying whether they match
+    pub text: bool,
+    // --no-mmap: always read files through a buffer, even big ones
+    pub no_mmap: bool,
+    // --watch: keep searching the files as they change instead of exiting
+    pub watch: bool,
+}
+
+// when to highlight matches with ANSI colors
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    // only when stdout is a terminal, so colors don't end up in files and pipes
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn enabled(self, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Auto => is_terminal,
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+/// What the command line asks for: a search, or one of the flags that print something and exit.
+pub enum Command {
+    // boxed, as a Config is far bigger than the other two
+    Run(Box<Config>),
+    // --help, see help
+    Help,
+    // --version
+    Version,
+}
+
+/* The first step: extract the parsing logic from the main to a func */
+// fn parse_config(args: &[String]) -> Config {
+//     // clone makes it straightforward(managing the lifetimes of the refs isn't necessary) but takes a bit more time to process at runtime so performance gets worse
+//     // it's a trade-off and which one to choose depends on things like the scale of your project
+//     let query = args[1].clone();
+//     let file_path = args[2].clone();
+
+//     Config { query, file_path }
+// }
+
+impl Config {
+    pub fn build(
+        /* args: &[String] */ mut args: impl Iterator<Item = String>,
+    ) -> Result<Command, MinigrepError> {
+        // the fist arg returned from env::args is the name of the program, so go one step further in advance
+        args.next();
+
+        // the env var only sets the default; -i and -s on the command line have the final say
+        let mut ignore_case = env::var("IGNORE_CASE").is_ok();
+        let mut use_regex = false;
+        let mut word_regexp = false;
+        let mut fuzzy = None;
+        let mut print_options = PrintOptions::default();
+        let mut invert_match = false;
+        let mut output_mode = OutputMode::Lines;
+        let mut color = ColorChoice::Auto;
+        let mut jobs = 1;
+        let mut walk_options = WalkOptions::default();
+        let mut replace = None;
+        let mut in_place = false;
+        let mut decompress = false;
+        let mut encoding = None;
+        let mut text = false;
+        let mut no_mmap = false;
+        let mut watch = false;
+        // -e and -f; when neither is given the first positional arg is the query
+        let mut patterns = Vec::new();
+        let mut pattern_flags = false;
+        // globs are relative to where minigrep runs from, like the paths on the command line
+        let mut overrides = OverrideBuilder::new(".");
+        let mut types = TypesBuilder::new();
+        types.add_defaults();
+        let (mut before, mut after, mut around) = (None, None, None);
+        let mut positional = Vec::new();
+
+        // flags may come before, between or after the positional args
+        let mut parser = ArgParser::new(args);
+        while let Some(token) = parser.next_token()? {
+            let (flag, spelled, value) = match token {
+                Token::Positional(arg) => {
+                    positional.push(arg);
+                    continue;
+                }
+                Token::Flag {
+                    flag,
+                    spelled,
+                    value,
+                } => (flag, spelled, value.unwrap_or_default()),
+            };
+
+            match flag.long {
+                "help" => return Ok(Command::Help),
+                "version" => return Ok(Command::Version),
+                "ignore-case" => ignore_case = true,
+                "case-sensitive" => ignore_case = false,
+                "regex" => use_regex = true,
+                "word-regexp" => word_regexp = true,
+                "fuzzy" => fuzzy = Some(parse_number(spelled, value)?),
+                "line-number" => print_options.line_number = true,
+                "show-pattern" => print_options.show_pattern = true,
+                "after-context" => after = Some(parse_number(spelled, value)?),
+                "before-context" => before = Some(parse_number(spelled, value)?),
+                "context" => around = Some(parse_number(spelled, value)?),
+                "invert-match" => invert_match = true,
+                // like the rest of the flags, the last mode given wins
+                "count" => output_mode = OutputMode::Count,
+                "files-with-matches" => output_mode = OutputMode::FilesWithMatches,
+                "files-without-match" => output_mode = OutputMode::FilesWithoutMatch,
+                "json" => output_mode = OutputMode::Json,
+                "jobs" => {
+                    jobs = match parse_number(spelled.clone(), value.clone())? {
+                        0 => {
+                            return Err(MinigrepError::InvalidValue {
+                                flag: spelled,
+                                value,
+                            })
+                        }
+                        n => n,
+                    }
+                }
+                "regexp" => {
+                    patterns.push(value);
+                    pattern_flags = true;
+                }
+                // one pattern per line, like grep -f; an empty file means there's nothing to match at all
+                "file" => {
+                    let contents =
+                        fs::read_to_string(&value).map_err(|source| MinigrepError::Io {
+                            path: value.into(),
+                            source,
+                        })?;
+                    patterns.extend(contents.lines().map(String::from));
+                    pattern_flags = true;
+                }
+                "replace" => replace = Some(value),
+                "in-place" => in_place = true,
+                "decompress" => decompress = true,
+                // any label the WHATWG encoding standard knows, e.g. utf-16le, latin1 or shift_jis
+                "encoding" => match Encoding::for_label(value.as_bytes()) {
+                    Some(label) => encoding = Some(label),
+                    None => {
+                        return Err(MinigrepError::InvalidValue {
+                            flag: spelled,
+                            value,
+                        })
+                    }
+                },
+                "text" => text = true,
+                "no-mmap" => no_mmap = true,
+                "watch" => watch = true,
+                "hidden" => walk_options.hidden = true,
+                "no-ignore" => walk_options.no_ignore = true,
+                // a leading ! is how the ignore crate spells an exclusion, so --exclude is just --glob with one added
+                "glob" | "exclude" => {
+                    let glob = match flag.long {
+                        "exclude" => format!("!{value}"),
+                        _ => value.clone(),
+                    };
+                    if overrides.add(&glob).is_err() {
+                        return Err(MinigrepError::InvalidValue {
+                            flag: spelled,
+                            value,
+                        });
+                    }
+                }
+                "type" => {
+                    types.select(&value);
+                    // unknown names only surface when the types are built, so check each one as it comes
+                    if TypesBuilder::new()
+                        .add_defaults()
+                        .select(&value)
+                        .build()
+                        .is_err()
+                    {
+                        return Err(MinigrepError::InvalidValue {
+                            flag: spelled,
+                            value,
+                        });
+                    }
+                }
+                "color" => {
+                    color = match value.as_str() {
+                        "auto" => ColorChoice::Auto,
+                        "always" => ColorChoice::Always,
+                        "never" => ColorChoice::Never,
+                        _ => {
+                            return Err(MinigrepError::InvalidValue {
+                                flag: spelled,
+                                value,
+                            })
+                        }
+                    }
+                }
+                _ => unreachable!("flag --{} is in FLAGS but not handled", flag.long),
+            }
+        }
+
+        if in_place && replace.is_none() {
+            return Err(MinigrepError::FlagNeeds {
+                flag: "--in-place",
+                needs: "--replace",
+            });
+        }
+
+        // an edit distance is measured against one plain string, not a pattern or a set of them
+        if fuzzy.is_some() && use_regex {
+            return Err(MinigrepError::FlagConflict {
+                flag: "--fuzzy",
+                with: "'--regex'",
+            });
+        }
+
+        // --watch only ever prints what it finds; it has no way to show or write a file's replacements as it changes
+        if watch && replace.is_some() {
+            return Err(MinigrepError::FlagConflict {
+                flag: "--watch",
+                with: "'--replace'",
+            });
+        }
+
+        // like grep, an explicit -A or -B wins over -C no matter the order they were given in
+        print_options.after_context = after.or(around).unwrap_or(0);
+        print_options.before_context = before.or(around).unwrap_or(0);
+
+        // both were checked flag by flag above, so building them can't fail
+        walk_options.overrides = overrides
+            .build()
+            .expect("globs were validated as they were added");
+        walk_options.types = types
+            .build()
+            .expect("types were validated as they were selected");
+
+        let mut args = positional.into_iter();
+
+        if !pattern_flags {
+            match args.next() {
+                Some(arg) => patterns.push(arg),
+                None => return Err(MinigrepError::MissingQuery),
+            }
+        }
+
+        // everything after the query (or all of it, with -e or -f) is a path to search; with none at all, read stdin
+        // unless it's a terminal, where waiting for input would just look like a hang
+        let mut file_paths: Vec<String> = args.collect();
+        if file_paths.is_empty() {
+            if io::stdin().is_terminal() {
+                return Err(MinigrepError::MissingPath);
+            }
+            file_paths.push("-".to_string());
+        }
+        // stdin can't be read again once it's been searched, so there'd be nothing to watch
+        if watch && file_paths.iter().any(|path| path == "-") {
+            return Err(MinigrepError::FlagConflict {
+                flag: "--watch",
+                with: "standard input",
+            });
+        }
+
+        if fuzzy.is_some() && patterns.len() != 1 {
+            return Err(MinigrepError::FlagConflict {
+                flag: "--fuzzy",
+                with: "more than one pattern",
+            });
+        }
+
+        // a single literal pattern needs nothing compiled, see matcher
+        // several of them are searched for all at once with Aho–Corasick; with -i that runs over the folded line,
+        // since Aho–Corasick's own case insensitivity only folds ASCII
+        let multiple = patterns.len() != 1;
+        let (regex, literals, folded_literals) = if use_regex {
+            let regexes = patterns
+                .iter()
+                .map(|pattern| {
+                    compile(pattern, ignore_case).map_err(|source| MinigrepError::InvalidPattern {
+                        pattern: pattern.clone(),
+                        source,
+                    })
+                })
+                .collect::<Result<Vec<Regex>, MinigrepError>>()?;
+
+            let regex = match regexes.len() {
+                1 => RegexMatcher::from(regexes.into_iter().next().unwrap()),
+                _ => RegexMatcher::any_of(regexes).map_err(|source| {
+                    MinigrepError::InvalidPattern {
+                        pattern: patterns.join("|"),
+                        source,
+                    }
+                })?,
+            };
+            (Some(regex), None, None)
+        } else if multiple && ignore_case {
+            let folded =
+                CaseInsensitiveMatcher::any_of(&patterns).map_err(MinigrepError::PatternSet)?;
+            (None, None, Some(folded))
+        } else if multiple {
+            let literals =
+                MultiLiteralMatcher::new(&patterns).map_err(MinigrepError::PatternSet)?;
+            (None, Some(literals), None)
+        } else {
+            (None, None, None)
+        };
+
+        // if args.len() < 3 {
+        //     // panic!("not enough arguments");
+        //     return Err("not enough arguments");
+        // }
+        // let query = args[1].clone();
+        // let file_path = args[2].clone();
+        // let ignore_case = env::var("IGNORE_CASE").is_ok();
+
+        Ok(Command::Run(Box::new(Config {
+            patterns,
+            file_paths,
+            ignore_case,
+            regex,
+            literals,
+            folded_literals,
+            word_regexp,
+            fuzzy,
+            print_options,
+            invert_match,
+            output_mode,
+            color,
+            jobs,
+            walk_options,
+            replace,
+            in_place,
+            decompress,
+            encoding,
+            text,
+            no_mmap,
+            watch,
+        })))
+    }
+}
+
+impl Config {
+    // the kind of matching the flags asked for
+    // Send + Sync so that -j can share it between worker threads
+    pub fn matcher(&self) -> Box<dyn Matcher + Send + Sync> {
+        // a compiled pattern takes precedence; it already knows whether to ignore case
+        let matcher: Box<dyn Matcher + Send + Sync> = if let Some(regex) = &self.regex {
+            Box::new(regex.clone())
+        } else if let Some(literals) = &self.literals {
+            Box::new(literals.clone())
+        } else if let Some(folded) = &self.folded_literals {
+            Box::new(folded.clone())
+        } else if let Some(distance) = self.fuzzy {
+            Box::new(FuzzyMatcher::new(&self.patterns[0], distance).ignore_case(self.ignore_case))
+        } else if self.ignore_case {
+            Box::new(CaseInsensitiveMatcher::new(&self.patterns[0]))
+        } else {
+            Box::new(LiteralMatcher::new(&self.patterns[0]))
+        };
+
+        if self.word_regexp {
+            Box::new(WordMatcher::new(matcher))
+        } else {
+            matcher
+        }
+    }
+}
+
+// (?i: ...) rather than RegexBuilder::case_insensitive, so that the flag survives being put into an alternation by any_of
+fn compile(pattern: &str, ignore_case: bool) -> Result<Regex, regex::Error> {
+    if ignore_case {
+        Regex::new(&format!("(?i:{pattern})"))
+    } else {
+        Regex::new(pattern)
+    }
+}
+
+fn parse_number(flag: String, value: String) -> Result<usize, MinigrepError> {
+    value
+        .parse()
+        .map_err(|_| MinigrepError::InvalidValue { flag, value })
+}
+
+// one entry per flag; both the parser and --help read from this table, so they can't drift apart
+struct Flag {
+    short: Option<char>,
+    long: &'static str,
+    // the placeholder shown in --help for flags that take a value, None for plain switches
+    value: Option<&'static str>,
+    help: &'static str,
+}
+
+const FLAGS: &[Flag] = &[
+    Flag {
+        short: Some('i'),
+        long: "ignore-case",
+        value: None,
+        help: "Match case-insensitively (the default when IGNORE_CASE is set)",
+    },
+    Flag {
+        short: Some('s'),
+        long: "case-sensitive",
+        value: None,
+        help: "Match case-sensitively even when IGNORE_CASE is set",
+    },
+    Flag {
+        short: None,
+        long: "regex",
+        value: None,
+        help: "Treat QUERY as a regular expression",
+    },
+    Flag {
+        short: Some('w'),
+        long: "word-regexp",
+        value: None,
+        help: "Only match whole words",
+    },
+    Flag {
+        short: None,
+        long: "fuzzy",
+        value: Some("NUM"),
+        help: "Also match text up to NUM edits away from QUERY",
+    },
+    Flag {
+        short: Some('e'),
+        long: "regexp",
+        value: Some("PATTERN"),
+        help: "Search for PATTERN (may be repeated; QUERY is then a PATH)",
+    },
+    Flag {
+        short: Some('f'),
+        long: "file",
+        value: Some("FILE"),
+        help: "Search for each line of FILE as a pattern (may be repeated)",
+    },
+    Flag {
+        short: Some('n'),
+        long: "line-number",
+        value: None,
+        help: "Prefix each line with its line number",
+    },
+    Flag {
+        short: None,
+        long: "show-pattern",
+        value: None,
+        help: "Prefix each selected line with the patterns that matched it",
+    },
+    Flag {
+        short: Some('A'),
+        long: "after-context",
+        value: Some("NUM"),
+        help: "Print NUM lines after each match",
+    },
+    Flag {
+        short: Some('B'),
+        long: "before-context",
+        value: Some("NUM"),
+        help: "Print NUM lines before each match",
+    },
+    Flag {
+        short: Some('C'),
+        long: "context",
+        value: Some("NUM"),
+        help: "Print NUM lines before and after each match",
+    },
+    Flag {
+        short: Some('v'),
+        long: "invert-match",
+        value: None,
+        help: "Select the lines that don't match",
+    },
+    Flag {
+        short: Some('c'),
+        long: "count",
+        value: None,
+        help: "Print only the number of selected lines per file",
+    },
+    Flag {
+        short: Some('l'),
+        long: "files-with-matches",
+        value: None,
+        help: "Print only the names of files with selected lines",
+    },
+    Flag {
+        short: Some('L'),
+        long: "files-without-match",
+        value: None,
+        help: "Print only the names of files without selected lines",
+    },
+    Flag {
+        short: None,
+        long: "json",
+        value: None,
+        help: "Print each selected line as a JSON object, then a summary",
+    },
+    Flag {
+        short: None,
+        long: "color",
+        value: Some("WHEN"),
+        help: "Highlight matches: auto (on a terminal), always or never",
+    },
+    Flag {
+        short: Some('j'),
+        long: "jobs",
+        value: Some("NUM"),
+        help: "Search NUM files at once (output order is unaffected)",
+    },
+    Flag {
+        short: Some('z'),
+        long: "decompress",
+        value: None,
+        help: "Decompress every input as gzip (compressed files are detected anyway)",
+    },
+    Flag {
+        short: None,
+        long: "encoding",
+        value: Some("ENC"),
+        help: "Decode input without a BOM as ENC, e.g. latin1 or utf-16le",
+    },
+    Flag {
+        short: Some('a'),
+        long: "text",
+        value: None,
+        help: "Search binary files as text instead of only saying they match",
+    },
+    Flag {
+        short: None,
+        long: "no-mmap",
+        value: None,
+        help: "Never memory-map files, even big ones",
+    },
+    Flag {
+        short: None,
+        long: "watch",
+        value: None,
+        help: "Keep running, printing matches in lines appended to the files",
+    },
+    Flag {
+        short: None,
+        long: "replace",
+        value: Some("TEXT"),
+        help: "Replace each match with TEXT ($1 etc. with --regex) and print a diff",
+    },
+    Flag {
+        short: None,
+        long: "in-place",
+        value: None,
+        help: "With --replace, rewrite the files instead of printing a diff",
+    },
+    Flag {
+        short: None,
+        long: "hidden",
+        value: None,
+        help: "Search hidden files and directories too",
+    },
+    Flag {
+        short: None,
+        long: "no-ignore",
+        value: None,
+        help: "Don't skip files listed in .gitignore, .ignore and the like",
+    },
+    Flag {
+        short: Some('g'),
+        long: "glob",
+        value: Some("GLOB"),
+        help: "Only search files matching GLOB (may be repeated)",
+    },
+    Flag {
+        short: None,
+        long: "exclude",
+        value: Some("GLOB"),
+        help: "Skip files and directories matching GLOB (may be repeated)",
+    },
+    Flag {
+        short: Some('t'),
+        long: "type",
+        value: Some("TYPE"),
+        help: "Only search files of TYPE, e.g. rust or py (may be repeated)",
+    },
+    Flag {
+        short: Some('h'),
+        long: "help",
+        value: None,
+        help: "Print this help and exit",
+    },
+    Flag {
+        short: Some('V'),
+        long: "version",
+        value: None,
+        help: "Print the version and exit",
+    },
+];
+
+pub fn help() -> String {
+    let mut text = String::from(
+        "Usage: minigrep [OPTIONS] QUERY [PATH]...\n\
+         \x20      minigrep [OPTIONS] -e PATTERN... [PATH]...\n\n\
+         Search for QUERY in each PATH. Directories are searched recursively.\n\
+         With no PATH, or when PATH is -, read standard input.\n\
+         Exits with 0 if a line is selected, 1 if none is, and 2 if an error occurred.\n\
+         A search that can't get going exits with 2 for bad arguments, 3 for an invalid pattern,\n\
+         4 for input that can't be read and 5 for output that can't be written.\n\n\
+         Options:\n",
+    );
+
+    for flag in FLAGS {
+        let short = flag.short.map(|c| format!("-{c}, ")).unwrap_or_default();
+        let long = match flag.value {
+            Some(value) => format!("--{} <{value}>", flag.long),
+            None => format!("--{}", flag.long),
+        };
+        text.push_str(&format!("  {short:4}{long:26}{}\n", flag.help));
+    }
+
+    text.trim_end().to_string()
+}
+
+enum Token {
+    Positional(String),
+    // spelled is the flag the way it was typed (-A or --after-context) so errors can echo it back
+    Flag {
+        flag: &'static Flag,
+        spelled: String,
+        value: Option<String>,
+    },
+}
+
+// splits raw args into flags and positional args
+// handles --long, --long=value, --long value, bundled short flags (-in), attached short values (-A3) and -- to end flags
+struct ArgParser<I> {
+    args: I,
+    // short flags still waiting to be read from a bundle like -inA3
+    bundle: Option<String>,
+    only_positional: bool,
+}
+
+impl<I: Iterator<Item = String>> ArgParser<I> {
+    fn new(args: I) -> ArgParser<I> {
+        ArgParser {
+            args,
+            bundle: None,
+            only_positional: false,
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, MinigrepError> {
+        if let Some(bundle) = self.bundle.take() {
+            return self.short(bundle).map(Some);
+        }
+
+        let arg = match self.args.next() {
+            Some(arg) => arg,
+            None => return Ok(None),
+        };
+
+        // a lone - is a path (stdin by convention), not a flag
+        if self.only_positional || arg == "-" || !arg.starts_with('-') {
+            return Ok(Some(Token::Positional(arg)));
+        }
+        if arg == "--" {
+            self.only_positional = true;
+            return self.next_token();
+        }
+
+        match arg.strip_prefix("--") {
+            Some(long) => self.long(long).map(Some),
+            None => self.short(arg[1..].to_string()).map(Some),
+        }
+    }
+
+    fn long(&mut self, arg: &str) -> Result<Token, MinigrepError> {
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (arg, None),
+        };
+        let spelled = format!("--{name}");
+
+        let flag = match FLAGS.iter().find(|f| f.long == name) {
+            Some(flag) => flag,
+            None => return Err(MinigrepError::UnknownFlag(spelled)),
+        };
+
+        let value = match (flag.value, inline) {
+            (None, Some(_)) => return Err(MinigrepError::UnexpectedValue(spelled)),
+            (None, None) => None,
+            (Some(_), Some(value)) => Some(value),
+            (Some(_), None) => match self.args.next() {
+                Some(value) => Some(value),
+                None => return Err(MinigrepError::MissingValue(spelled)),
+            },
+        };
+
+        Ok(Token::Flag {
+            flag,
+            spelled,
+            value,
+        })
+    }
+
+    fn short(&mut self, bundle: String) -> Result<Token, MinigrepError> {
+        let mut chars = bundle.chars();
+        // next_token never hands over an empty bundle: "-" alone is positional
+        let c = chars.next().unwrap();
+        let rest = chars.as_str();
+        let spelled = format!("-{c}");
+
+        let flag = match FLAGS.iter().find(|f| f.short == Some(c)) {
+            Some(flag) => flag,
+            None => return Err(MinigrepError::UnknownFlag(spelled)),
+        };
+
+        let value = if flag.value.is_none() {
+            // whatever follows is more short flags, e.g. the n in -in
+            if !rest.is_empty() {
+                self.bundle = Some(rest.to_string());
+            }
+            None
+        } else if !rest.is_empty() {
+            // the value is glued on, e.g. -A3
+            Some(rest.to_string())
+        } else {
+            match self.args.next() {
+                Some(value) => Some(value),
+                None => return Err(MinigrepError::MissingValue(spelled)),
+            }
+        };
+
+        Ok(Token::Flag {
+            flag,
+            spelled,
+            value,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    fn build(args: &[&str]) -> Result<Config, MinigrepError> {
+        let args = ["minigrep"].iter().chain(args).map(|arg| arg.to_string());
+        match Config::build(args)? {
+            Command::Run(config) => Ok(*config),
+            Command::Help | Command::Version => panic!("expected a search"),
+        }
+    }
+
+    #[test]
+    fn invalid_regex_is_rejected() {
+        let err = build(&["--regex", "(unclosed", "poem.txt"]).err().unwrap();
+
+        assert!(err
+            .to_string()
+            .starts_with("Invalid regular expression '(unclosed'"));
+        assert!(err.source().is_some());
+    }
+
+    #[test]
+    fn explicit_context_overrides_around() {
+        let config = build(&["-A", "1", "-C", "3", "-n", "rust", "poem.txt"]).unwrap();
+
+        assert!(config.print_options.line_number);
+        assert_eq!(1, config.print_options.after_context);
+        assert_eq!(3, config.print_options.before_context);
+    }
+
+    #[test]
+    fn flags_in_any_order_and_form() {
+        let config =
+            build(&["rust", "-niB2", "poem.txt", "--after-context=4", "--", "-v"]).unwrap();
+
+        assert_eq!(vec!["rust"], config.patterns);
+        assert_eq!(vec!["poem.txt", "-v"], config.file_paths);
+        assert!(config.ignore_case);
+        assert!(config.print_options.line_number);
+        assert_eq!(2, config.print_options.before_context);
+        assert_eq!(4, config.print_options.after_context);
+    }
+
+    #[test]
+    fn case_sensitive_flag_wins_over_ignore_case() {
+        let config = build(&["-i", "--case-sensitive", "rust", "poem.txt"]).unwrap();
+
+        assert!(!config.ignore_case);
+    }
+
+    #[test]
+    fn several_patterns_ignoring_case_fold_all_of_unicode() {
+        let config = build(&["-i", "-e", "STRASSE", "-e", "nope", "poem.txt"]).unwrap();
+        let matcher = config.matcher();
+
+        assert!(matcher.is_match("Große Straße 1"));
+        assert!(matcher.is_match("NOPE"));
+        assert_eq!(0, matcher.pattern_of("Große Straße 1", 7..14));
+    }
+
+    #[test]
+    fn bad_flags_get_specific_errors() {
+        assert!(matches!(
+            build(&["-x", "rust", "poem.txt"]),
+            Err(MinigrepError::UnknownFlag(flag)) if flag == "-x"
+        ));
+        assert!(matches!(
+            build(&["rust", "poem.txt", "--context"]),
+            Err(MinigrepError::MissingValue(flag)) if flag == "--context"
+        ));
+        assert!(matches!(
+            build(&["--regex=yes", "rust", "poem.txt"]),
+            Err(MinigrepError::UnexpectedValue(flag)) if flag == "--regex"
+        ));
+        assert!(matches!(
+            build(&["-A", "many", "rust", "poem.txt"]),
+            Err(MinigrepError::InvalidValue { flag, value }) if flag == "-A" && value == "many"
+        ));
+        assert!(matches!(
+            build(&["--color=sometimes", "rust", "poem.txt"]),
+            Err(MinigrepError::InvalidValue { flag, .. }) if flag == "--color"
+        ));
+        assert!(matches!(
+            build(&["-j0", "rust", "poem.txt"]),
+            Err(MinigrepError::InvalidValue { flag, .. }) if flag == "-j"
+        ));
+        assert!(matches!(
+            build(&["-t", "klingon", "rust", "poem.txt"]),
+            Err(MinigrepError::InvalidValue { flag, value }) if flag == "-t" && value == "klingon"
+        ));
+        assert!(matches!(
+            build(&["--exclude", "a{b", "rust", "poem.txt"]),
+            Err(MinigrepError::InvalidValue { flag, .. }) if flag == "--exclude"
+        ));
+        assert!(matches!(
+            build(&["--in-place", "rust", "poem.txt"]),
+            Err(MinigrepError::FlagNeeds {
+                flag: "--in-place",
+                ..
+            })
+        ));
+        assert!(matches!(
+            build(&["--encoding", "klingon", "rust", "poem.txt"]),
+            Err(MinigrepError::InvalidValue { flag, .. }) if flag == "--encoding"
+        ));
+        assert!(matches!(
+            build(&["--fuzzy", "1", "--regex", "rust", "poem.txt"]),
+            Err(MinigrepError::FlagConflict {
+                flag: "--fuzzy",
+                ..
+            })
+        ));
+        assert!(matches!(
+            build(&["--fuzzy", "1", "-e", "rust", "-e", "go", "poem.txt"]),
+            Err(MinigrepError::FlagConflict {
+                flag: "--fuzzy",
+                ..
+            })
+        ));
+        assert!(matches!(
+            build(&[
+                "--watch",
+                "--replace",
+                "x",
+                "--in-place",
+                "rust",
+                "poem.txt"
+            ]),
+            Err(MinigrepError::FlagConflict {
+                flag: "--watch",
+                with: "'--replace'",
+            })
+        ));
+        assert!(matches!(
+            build(&["--watch", "rust", "poem.txt", "-"]),
+            Err(MinigrepError::FlagConflict {
+                flag: "--watch",
+                with: "standard input",
+            })
+        ));
+        assert!(matches!(build(&["-n"]), Err(MinigrepError::MissingQuery)));
+    }
+
+    #[test]
+    fn help_and_version_stop_parsing() {
+        let command = |args: &[&str]| {
+            let args = ["minigrep"].iter().chain(args).map(|arg| arg.to_string());
+            Config::build(args)
+        };
+
+        assert!(matches!(command(&["--help", "rust"]), Ok(Command::Help)));
+        assert!(matches!(command(&["rust", "-V"]), Ok(Command::Version)));
+        assert!(matches!(
+            command(&["rust", "poem.txt"]),
+            Ok(Command::Run(_))
+        ));
+    }
+}
@@ -0,0 +1,168 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::display_name;
+
+/// Everything that can go wrong in minigrep, from parsing the arguments to writing the results.
+///
+/// [`Config::build`](crate::Config::build) and [`run`](crate::run) return it, and so do the per-file
+/// warnings a run prints and then carries on from.
+/// [`exit_code`](MinigrepError::exit_code) tells the kinds apart for scripts.
+#[derive(Debug)]
+pub enum MinigrepError {
+    MissingQuery,
+    MissingPath,
+    UnknownFlag(String),
+    MissingValue(String),
+    UnexpectedValue(String),
+    InvalidValue {
+        flag: String,
+        value: String,
+    },
+    // a flag that only makes sense together with another one
+    FlagNeeds {
+        flag: &'static str,
+        needs: &'static str,
+    },
+    // and one that doesn't make sense together with something else
+    FlagConflict {
+        flag: &'static str,
+        with: &'static str,
+    },
+    InvalidPattern {
+        pattern: String,
+        source: regex::Error,
+    },
+    // too many -e/-f patterns for the automaton to hold
+    PatternSet(aho_corasick::BuildError),
+    // a file (or directory, or -f pattern file) that couldn't be read; path is - for stdin
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    // --replace needs real text; searches read invalid UTF-8 lossily and never get this
+    InvalidUtf8 {
+        path: PathBuf,
+        line_number: usize,
+    },
+    // --replace won't touch binary files
+    Binary {
+        path: PathBuf,
+    },
+    // --in-place can only write back what it read, see replace
+    NotEditable {
+        path: PathBuf,
+        reason: &'static str,
+    },
+    // the results couldn't be written, e.g. stdout was closed
+    Output(io::Error),
+}
+
+impl MinigrepError {
+    /// The status the process should exit with.
+    ///
+    /// 2 for bad arguments, 3 for an invalid pattern, 4 for input that can't be read
+    /// and 5 for output that can't be written.
+    /// (A run that gets going exits with 0, 1 or 2 instead, see [`Summary::exit_code`](crate::Summary::exit_code).)
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            MinigrepError::MissingQuery
+            | MinigrepError::MissingPath
+            | MinigrepError::UnknownFlag(_)
+            | MinigrepError::MissingValue(_)
+            | MinigrepError::UnexpectedValue(_)
+            | MinigrepError::InvalidValue { .. }
+            | MinigrepError::FlagNeeds { .. }
+            | MinigrepError::FlagConflict { .. } => 2,
+            MinigrepError::InvalidPattern { .. } | MinigrepError::PatternSet(_) => 3,
+            MinigrepError::Io { .. }
+            | MinigrepError::InvalidUtf8 { .. }
+            | MinigrepError::Binary { .. }
+            | MinigrepError::NotEditable { .. } => 4,
+            MinigrepError::Output(_) => 5,
+        }
+    }
+}
+
+impl fmt::Display for MinigrepError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MinigrepError::MissingQuery => write!(f, "Didn't get a query string"),
+            MinigrepError::MissingPath => write!(f, "Didn't get a file path"),
+            MinigrepError::UnknownFlag(flag) => write!(f, "Unknown flag '{flag}'"),
+            MinigrepError::MissingValue(flag) => write!(f, "Flag '{flag}' needs a value"),
+            MinigrepError::UnexpectedValue(flag) => {
+                write!(f, "Flag '{flag}' doesn't take a value")
+            }
+            MinigrepError::InvalidValue { flag, value } => {
+                write!(f, "Invalid value for '{flag}': '{value}'")
+            }
+            MinigrepError::FlagNeeds { flag, needs } => {
+                write!(f, "Flag '{flag}' can only be used with '{needs}'")
+            }
+            MinigrepError::FlagConflict { flag, with } => {
+                write!(f, "Flag '{flag}' can't be used with {with}")
+            }
+            MinigrepError::InvalidPattern { pattern, source } => {
+                write!(f, "Invalid regular expression '{pattern}': {source}")
+            }
+            MinigrepError::PatternSet(source) => {
+                write!(f, "Couldn't build the pattern set: {source}")
+            }
+            // like grep, the file comes first, so a run's warnings line up
+            MinigrepError::Io { path, source } => write!(f, "{}: {source}", display_name(path)),
+            MinigrepError::InvalidUtf8 { path, line_number } => {
+                write!(
+                    f,
+                    "{}: line {line_number} is not valid UTF-8",
+                    display_name(path)
+                )
+            }
+            MinigrepError::Binary { path } => {
+                write!(f, "{}: binary file, skipping", display_name(path))
+            }
+            MinigrepError::NotEditable { path, reason } => {
+                write!(f, "{}: {reason}", display_name(path))
+            }
+            MinigrepError::Output(source) => write!(f, "Couldn't write the results: {source}"),
+        }
+    }
+}
+
+impl Error for MinigrepError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MinigrepError::InvalidPattern { source, .. } => Some(source),
+            MinigrepError::PatternSet(source) => Some(source),
+            MinigrepError::Io { source, .. } => Some(source),
+            MinigrepError::Output(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn io_errors_name_the_file_and_keep_their_cause() {
+        let err = MinigrepError::Io {
+            path: PathBuf::from("-"),
+            source: io::Error::new(
+                io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            ),
+        };
+
+        assert_eq!(
+            "(standard input): stream did not contain valid UTF-8",
+            err.to_string()
+        );
+        assert!(err.source().is_some());
+        assert_eq!(4, err.exit_code());
+        assert_eq!(2, MinigrepError::MissingQuery.exit_code());
+    }
+}
@@ -0,0 +1,168 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use encoding_rs_io::DecodeReaderBytesBuilder;
+use flate2::bufread::MultiGzDecoder;
+use memmap2::Mmap;
+
+use crate::{is_stdin, Config};
+
+// every gzip stream starts with these two bytes
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+// UTF-8, UTF-16LE and UTF-16BE byte order marks
+const BOMS: [&[u8]; 3] = [&[0xef, 0xbb, 0xbf], &[0xff, 0xfe], &[0xfe, 0xff]];
+// below this, mapping a file costs more than it saves
+const MMAP_THRESHOLD: u64 = 1024 * 1024;
+
+// something to search, opened and ready to be read line by line
+pub struct Input {
+    pub reader: Box<dyn BufRead>,
+    // whether reader decompresses what's actually stored, which --in-place has to know about
+    pub compressed: bool,
+    // likewise for text that gets converted to UTF-8 (or loses its BOM) on the way
+    pub transcoded: bool,
+}
+
+// opens a file, or stdin for -, the way every output mode and --replace read their input
+// compressed input is recognised by its first bytes rather than by its name, so rotated logs like syslog.2.gz and
+// a gzip stream piped in on stdin are both searched as the text inside them
+// with -z the input is decompressed even if it doesn't look compressed, which turns a corrupt file into an error
+// the text is then decoded to UTF-8, see decode
+pub fn open(path: &Path, config: &Config) -> io::Result<Input> {
+    let input = if is_stdin(path) {
+        decompress(io::stdin().lock(), config.decompress)?
+    } else {
+        decompress(BufReader::new(File::open(path)?), config.decompress)?
+    };
+
+    decode(input, config.encoding)
+}
+
+// maps a big plain file into memory so it can be searched in place, without copying it through a buffer
+// None for anything that has to go through open instead: stdin, small files, and compressed or non-UTF-8 text
+pub fn map(path: &Path, config: &Config) -> io::Result<Option<Mmap>> {
+    if is_stdin(path) || config.no_mmap || config.decompress || config.encoding.is_some() {
+        return Ok(None);
+    }
+    // a stat is all it takes to rule out most files, so don't open them twice
+    let meta = fs::metadata(path)?;
+    if !meta.is_file() || meta.len() < MMAP_THRESHOLD {
+        return Ok(None);
+    }
+
+    let file = File::open(path)?;
+    // SAFETY: the map is only read, and only while this file is being searched
+    // if another process truncates the file in the meantime, reading past the new end is a SIGBUS;
+    // ripgrep and grep take the same risk, and --no-mmap is there for files that might shrink
+    let map = unsafe { Mmap::map(&file)? };
+
+    if map.starts_with(&GZIP_MAGIC) || BOMS.iter().any(|bom| map.starts_with(bom)) {
+        return Ok(None);
+    }
+    Ok(Some(map))
+}
+
+fn decompress<R: BufRead + 'static>(mut reader: R, force: bool) -> io::Result<Input> {
+    // peeking through the buffer leaves the bytes in place for whoever reads next
+    let compressed = force || reader.fill_buf()?.starts_with(&GZIP_MAGIC);
+
+    let reader: Box<dyn BufRead> = if compressed {
+        // Multi so that concatenated gzip members (cat a.gz b.gz) are read through to the end
+        Box::new(BufReader::new(MultiGzDecoder::new(reader)))
+    } else {
+        Box::new(reader)
+    };
+
+    Ok(Input {
+        reader,
+        compressed,
+        transcoded: false,
+    })
+}
+
+// a BOM decides the encoding: UTF-16 gets converted to UTF-8, and a UTF-8 BOM is dropped so it can't end up in a match
+// without one, text is taken to be in --encoding if that was given, and is passed through untouched otherwise,
+// leaving any invalid UTF-8 to the searcher
+fn decode(mut input: Input, encoding: Option<&'static encoding_rs::Encoding>) -> io::Result<Input> {
+    let start = input.reader.fill_buf()?;
+    let has_bom = BOMS.iter().any(|bom| start.starts_with(bom));
+    if !has_bom && encoding.is_none() {
+        return Ok(input);
+    }
+
+    let decoder = DecodeReaderBytesBuilder::new()
+        .encoding(encoding)
+        .bom_override(true)
+        .strip_bom(true)
+        .build(input.reader);
+
+    Ok(Input {
+        reader: Box::new(BufReader::new(decoder)),
+        compressed: input.compressed,
+        transcoded: true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::{Read, Write};
+
+    fn gzip(text: &str) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(text.as_bytes()).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn read(input: Input) -> String {
+        let mut text = String::new();
+        let mut reader = input.reader;
+        reader.read_to_string(&mut text).unwrap();
+        text
+    }
+
+    #[test]
+    fn gzip_is_recognised_by_its_magic_bytes() {
+        let mut members = gzip("one\n");
+        members.extend(gzip("two\n"));
+
+        let input = decompress(io::Cursor::new(members), false).unwrap();
+        assert!(input.compressed);
+        assert_eq!("one\ntwo\n", read(input));
+
+        let input = decompress(io::Cursor::new("plain\n"), false).unwrap();
+        assert!(!input.compressed);
+        assert_eq!("plain\n", read(input));
+    }
+
+    #[test]
+    fn utf16_with_a_bom_and_explicit_encodings_are_decoded() {
+        let utf16: Vec<u8> = [0xff, 0xfe]
+            .into_iter()
+            .chain("grüß\n".encode_utf16().flat_map(u16::to_le_bytes))
+            .collect();
+        let input = decompress(io::Cursor::new(utf16), false).unwrap();
+        let input = decode(input, None).unwrap();
+        assert!(input.transcoded);
+        assert_eq!("grüß\n", read(input));
+
+        let latin1 = decompress(io::Cursor::new(b"gr\xfc\xdf\n".to_vec()), false).unwrap();
+        let input = decode(latin1, Some(encoding_rs::WINDOWS_1252)).unwrap();
+        assert_eq!("grüß\n", read(input));
+
+        let plain = decompress(io::Cursor::new("plain\n"), false).unwrap();
+        assert!(!decode(plain, None).unwrap().transcoded);
+    }
+
+    #[test]
+    fn forcing_decompression_rejects_plain_text() {
+        let input = decompress(io::Cursor::new("plain\n"), true).unwrap();
+
+        let mut text = String::new();
+        let mut reader = input.reader;
+        assert!(reader.read_to_string(&mut text).is_err());
+    }
+}
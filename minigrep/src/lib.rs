@@ -1,10 +1,41 @@
-use std::env;
-use std::error::Error;
-use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::Path;
+
+use regex::Regex;
+
+mod config;
+mod error;
+mod input;
+mod matcher;
+mod output;
+mod parallel;
+mod replace;
+mod searcher;
+#[cfg(test)]
+mod test_support;
+mod walk;
+mod watch;
+
+pub use config::{help, ColorChoice, Command, Config};
+pub use error::MinigrepError;
+pub use matcher::{
+    CaseInsensitiveMatcher, FuzzyMatcher, LiteralMatcher, Matcher, MultiLiteralMatcher,
+    RegexMatcher, WordMatcher,
+};
+use output::Printer;
+pub use output::{OutputMode, PrintOptions};
+pub use searcher::{LineMatch, Searcher};
+pub use walk::WalkOptions;
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::{config, run_with, TempPath};
+    use std::fs;
+
+    fn lines(matches: Vec<Match<'_>>) -> Vec<&str> {
+        matches.into_iter().map(|m| m.line).collect()
+    }
 
     #[test]
     fn case_sensitive() {
@@ -15,7 +46,10 @@ safe, fast, productive.
 Pick three.
 Duct tape.";
 
-        assert_eq!(vec!["safe, fast, productive."], search(query, contents));
+        assert_eq!(
+            vec!["safe, fast, productive."],
+            lines(search(query, contents))
+        );
     }
 
     #[test]
@@ -29,111 +63,636 @@ Trust me.";
 
         assert_eq!(
             vec!["Rust:", "Trust me."],
-            search_case_insensitive(query, contents)
+            lines(search_case_insensitive(query, contents))
+        );
+    }
+
+    #[test]
+    fn regex_anchors_classes_and_alternation() {
+        let pattern = Regex::new(r"^(Pick|Duct) [a-z]+\.$").unwrap();
+        let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Duct tape.
+Not Pick three.";
+
+        assert_eq!(
+            vec!["Pick three.", "Duct tape."],
+            lines(search_regex(&pattern, contents))
+        );
+    }
+
+    #[test]
+    fn matches_carry_line_numbers_and_offsets() {
+        let contents = "Rust:\r\nsafe, fast, productive.\nPick three.\n";
+
+        assert_eq!(
+            vec![Match {
+                line_number: 3,
+                byte_offset: 31,
+                line: "Pick three.",
+            }],
+            search("Pick", contents)
+        );
+    }
+
+    #[test]
+    fn searches_a_reader_line_by_line() {
+        let config = config(&["-n", "-i", "rust", "-"]);
+        let searcher = Searcher::new(config.matcher()).invert_match(config.invert_match);
+        let reader = io::Cursor::new("Rust:\r\nsafe, fast, productive.\nTrust me.");
+        let mut printer = Printer::new(Vec::new(), config.print_options);
+
+        assert_eq!(
+            2,
+            search_reader(&config, &searcher, reader, &mut printer).unwrap()
+        );
+        assert_eq!(
+            "1:Rust:\n3:Trust me.\n",
+            String::from_utf8(printer.into_inner()).unwrap()
+        );
+    }
+
+    #[test]
+    fn binary_input_only_reports_a_match() {
+        let search = |args: &[&str], input: &[u8]| {
+            let config = config(&[args, &["a", "-"]].concat());
+            let searcher = Searcher::new(config.matcher()).lossy(true);
+            let mut printer = Printer::new(Vec::new(), config.print_options);
+            printer.begin_file("bin", false);
+            let count = search_reader(&config, &searcher, io::Cursor::new(input), &mut printer);
+            (
+                count.unwrap(),
+                String::from_utf8(printer.into_inner()).unwrap(),
+            )
+        };
+
+        assert_eq!(
+            (1, "Binary file bin matches\n".to_string()),
+            search(&[], b"a\0b\na\n")
+        );
+        assert_eq!((0, String::new()), search(&[], b"\0b\n"));
+        assert_eq!((2, String::new()), search(&["-c"], b"a\0b\na\n"));
+        assert_eq!(
+            (2, "a\0b\na\n".to_string()),
+            search(&["--text"], b"a\0b\na\n")
+        );
+        // invalid UTF-8 isn't binary, it's just decoded lossily
+        assert_eq!((1, "\u{fffd}a\n".to_string()), search(&[], b"b\n\xffa\n"));
+    }
+
+    #[test]
+    fn inverted_count_of_a_reader() {
+        let config = config(&["-vc", "rust", "-"]);
+        let searcher = Searcher::new(config.matcher()).invert_match(config.invert_match);
+        let reader = io::Cursor::new("rust\nsafe\nfast\ntrust\n");
+        let mut printer = Printer::new(Vec::new(), config.print_options);
+
+        assert_eq!(
+            2,
+            search_reader(&config, &searcher, reader, &mut printer).unwrap()
+        );
+        // counts are printed by run once the whole input has been read
+        assert!(printer.into_inner().is_empty());
+    }
+
+    #[test]
+    fn exit_codes_follow_grep() {
+        let summary = |matched, had_errors| Summary {
+            matched,
+            had_errors,
+        };
+
+        assert_eq!(0, summary(true, false).exit_code());
+        assert_eq!(1, summary(false, false).exit_code());
+        assert_eq!(2, summary(true, true).exit_code());
+    }
+
+    #[test]
+    fn case_insensitive_unicode() {
+        let query = "STRASSE";
+        let contents = "\
+Große Straße 1
+Kleine Strasse 2
+İstanbul Caddesi 3";
+
+        assert_eq!(
+            vec!["Große Straße 1", "Kleine Strasse 2"],
+            lines(search_case_insensitive(query, contents))
+        );
+        assert_eq!(
+            vec!["İstanbul Caddesi 3"],
+            lines(search_case_insensitive("ISTANBUL", contents))
+        );
+    }
+
+    #[test]
+    fn search_with_any_matcher() {
+        let matcher = WordMatcher::new(CaseInsensitiveMatcher::new("rust"));
+        let contents = "\
+Rust:
+safe, fast, productive.
+Trust me.";
+
+        assert_eq!(vec!["Rust:"], lines(search_with(&matcher, contents)));
+    }
+
+    #[test]
+    fn whole_words_and_fuzzy_matches() {
+        let contents = "\
+Rust:
+safe, fast, productive.
+Trust me.";
+
+        assert_eq!(vec!["Rust:"], lines(search_whole_words("Rust", contents)));
+        assert_eq!(
+            vec!["safe, fast, productive."],
+            lines(search_fuzzy("prodcutive", 2, contents))
+        );
+
+        assert_eq!(
+            "How public, like a frog\nTo an admiring bog!\n",
+            run_with(&["-w", "--fuzzy", "1", "fog", "poem.txt"])
+        );
+    }
+
+    #[test]
+    fn patterns_from_flags_and_files() {
+        let patterns = TempPath::new("patterns");
+        fs::write(&patterns, "nobody\nfrog\n").unwrap();
+
+        let expected = "\
+I'm nobody! Who are you?
+Are you nobody, too?
+How public, like a frog
+";
+        assert_eq!(
+            expected,
+            run_with(&["-e", "nobody", "-e", "frog", "poem.txt"])
         );
+        assert_eq!(expected, run_with(&["-f", patterns.arg(), "poem.txt"]));
+        assert_eq!(
+            expected,
+            run_with(&["-i", "-e", "NOBODY", "-e", "FROG", "poem.txt"])
+        );
+
+        let shown = "\
+1:nobody,Who:I'm nobody! Who are you?
+2:nobody:Are you nobody, too?
+7:frog:How public, like a frog
+";
+        assert_eq!(
+            shown,
+            run_with(&[
+                "--show-pattern",
+                "-n",
+                "-e",
+                "nobody",
+                "-e",
+                "frog",
+                "-e",
+                "Who",
+                "poem.txt"
+            ])
+        );
+    }
+
+    #[test]
+    fn mapped_files_search_like_buffered_ones() {
+        let path = TempPath::new("mmap.txt");
+        let mut contents = String::new();
+        for i in 0..40_000 {
+            match i % 1000 {
+                0 => contents.push_str("the needle is here\r\n"),
+                1 => contents.push_str("needles and needle\n"),
+                _ => contents.push_str("just some hay to search through\n"),
+            }
+        }
+        fs::write(&path, contents).unwrap();
+
+        for args in [&["-n"][..], &["-c"], &["-v", "-c"]] {
+            let mapped = [args, &["needle", path.arg()]].concat();
+            let no_mmap = [&mapped[..], &["--no-mmap"]].concat();
+            assert_eq!(run_with(&no_mmap), run_with(&mapped));
+        }
+        assert!(run_with(&["-n", "needle", path.arg()])
+            .starts_with("1:the needle is here\n2:needles and needle\n1001:"));
     }
 }
 
+// a single hit: where the line sits in the searched text, plus the line itself (without its line ending)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match<'a> {
+    // 1-based, like editors and grep -n count lines
+    pub line_number: usize,
+    // offset of the first byte of the line from the start of the text
+    pub byte_offset: usize,
+    pub line: &'a str,
+}
+
+// every line of contents together with its position
+// it splits lines exactly like str::lines does (on \n, dropping a trailing \r) but keeps track of where each one starts
+pub fn lines_with_positions(contents: &str) -> impl Iterator<Item = Match<'_>> {
+    let mut byte_offset = 0;
+
+    contents
+        .split_inclusive('\n')
+        .enumerate()
+        .map(move |(i, raw)| {
+            let line = raw.strip_suffix('\n').unwrap_or(raw);
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            let found = Match {
+                line_number: i + 1,
+                byte_offset,
+                line,
+            };
+            byte_offset += raw.len();
+            found
+        })
+}
+
 // arg contents' lifetime is connected to that of the return val
-pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
+pub fn search<'a>(query: &str, contents: &'a str) -> Vec<Match<'a>> {
     let mut results = Vec::new();
 
-    // the lines method returns an iterator
-    for line in contents.lines() {
-        if line.contains(query) {
-            results.push(line);
+    // the iterator hands out each line along with its number and offset
+    for found in lines_with_positions(contents) {
+        if found.line.contains(query) {
+            results.push(found);
         }
     }
 
     results
 
     // using an iter, you can omit the intermediate vec and the above code looks like this:
-    // contents
-    //     .lines()
-    //     .filter(|line| line.contains(query))
+    // lines_with_positions(contents)
+    //     .filter(|found| found.line.contains(query))
     //     .collect()
 }
 
-pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
-    let query = query.to_lowercase();
+pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<Match<'a>> {
+    // let query = query.to_lowercase();
+    // lowercasing misses folds like ß/SS and allocated a new String for every line, so fold char by char instead
+    let matcher = CaseInsensitiveMatcher::new(query);
     let mut results = Vec::new();
 
-    for line in contents.lines() {
-        if line.to_lowercase().contains(&query) {
-            results.push(line);
+    for found in lines_with_positions(contents) {
+        // if found.line.to_lowercase().contains(&query) {
+        if matcher.is_match(found.line) {
+            results.push(found);
         }
     }
 
     results
 }
 
-pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
+// same as search, but each line is tested against a compiled regex so anchors, classes and alternation work
+pub fn search_regex<'a>(pattern: &Regex, contents: &'a str) -> Vec<Match<'a>> {
+    lines_with_positions(contents)
+        .filter(|found| pattern.is_match(found.line))
+        .collect()
+}
+
+// like search, but only where the query is a whole word, so "rust" doesn't turn up in "trust"
+pub fn search_whole_words<'a>(query: &str, contents: &'a str) -> Vec<Match<'a>> {
+    search_with(&WordMatcher::new(LiteralMatcher::new(query)), contents)
+}
+
+// like search, but a line also matches if some part of it is up to max_distance typos away from the query
+pub fn search_fuzzy<'a>(query: &str, max_distance: usize, contents: &'a str) -> Vec<Match<'a>> {
+    search_with(&FuzzyMatcher::new(query, max_distance), contents)
+}
+
+// the general form of the ones above: any Matcher, e.g. a WordMatcher or one of your own
+pub fn search_with<'a>(matcher: &impl Matcher, contents: &'a str) -> Vec<Match<'a>> {
+    lines_with_positions(contents)
+        .filter(|found| matcher.is_match(found.line))
+        .collect()
+}
+
+// what a run turned up, which main turns into a grep-style exit status
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Summary {
+    // whether anything was selected: a (non-)matching line, or a file listed by -L
+    pub matched: bool,
+    // whether some input had to be skipped because of an error
+    pub had_errors: bool,
+}
+
+impl Summary {
+    // 0 when something was selected, 1 when nothing was, and 2 when an error got in the way (even if there were matches too)
+    pub fn exit_code(&self) -> i32 {
+        if self.had_errors {
+            2
+        } else if self.matched {
+            0
+        } else {
+            1
+        }
+    }
+}
+
+pub fn run(mut config: Config) -> Result<Summary, MinigrepError> {
     //dyn is short for dynamic
-    let contents = fs::read_to_string(config.file_path)?;
+    // lock stdout once for the whole run instead of once per println!
+    let stdout = io::stdout();
+
+    // only here is it known that the output is stdout, so this is where --color=auto gets settled
+    if config.color == ColorChoice::Auto && stdout.is_terminal() {
+        config.color = ColorChoice::Always;
+    }
+
+    if config.watch {
+        return watch::watch(&config, stdout.lock());
+    }
+    run_to(&config, stdout.lock())
+}
+
+// does what run does, but writes the results to out instead of stdout
+// out isn't known to be a terminal, so --color=auto means no colors here
+pub fn run_to<W: Write>(config: &Config, out: W) -> Result<Summary, MinigrepError> {
+    let mut summary = Summary::default();
+    let files = walk::collect_files(&config.file_paths, &config.walk_options, |path, source| {
+        warn(&MinigrepError::Io {
+            path: path.to_path_buf(),
+            source,
+        });
+        summary.had_errors = true;
+    });
+
+    // --replace prints a diff (or nothing at all, with --in-place) instead of lines, so -v, -j and the output modes don't apply
+    if let Some(replacement) = &config.replace {
+        let mut out = out;
+        replace::replace_files(
+            config,
+            &config.matcher(),
+            replacement,
+            &files,
+            &mut out,
+            &mut summary,
+        )?;
+        out.flush().map_err(MinigrepError::Output)?;
+        return Ok(summary);
+    }
+
+    // prefix each hit with its file as soon as more than one file could be involved, like grep does
+    let with_path = files.len() > 1 || config.file_paths.iter().any(|p| Path::new(p).is_dir());
+
+    // run doesn't care which kind of matching the flags asked for; the config hands back the right Matcher
+    // lines that aren't valid UTF-8 are still searched, the bad bytes showing up as U+FFFD; --encoding can do better
+    let searcher = Searcher::new(config.matcher())
+        .invert_match(config.invert_match)
+        .lossy(true);
+
+    let mut print_options = config.print_options;
+    print_options.color = config.color.enabled(false) && config.output_mode != OutputMode::Json;
+    print_options.json = config.output_mode == OutputMode::Json;
+    let mut printer = Printer::new(out, print_options);
+
+    if config.jobs > 1 && files.len() > 1 {
+        parallel::search_files(
+            config,
+            &searcher,
+            &files,
+            with_path,
+            &mut printer,
+            &mut summary,
+        )?;
+    } else {
+        for path in &files {
+            let name = display_name(path);
+            let result = search_path(config, &searcher, path, &name, with_path, &mut printer);
+            summary.record(result.map_err(|e| e.at(path)))?;
+        }
+    }
+
+    printer
+        .finish(summary.matched)
+        .and_then(|mut out| out.flush())
+        .map_err(MinigrepError::Output)?;
 
-    // println!("With text:\n{contents}");
+    Ok(summary)
+}
 
-    let results = if config.ignore_case {
-        search_case_insensitive(&config.query, &contents)
+impl Summary {
+    // one bad file shouldn't abort the whole run, so report it and move on to the next
+    // failing to write the results is different though: there's no point carrying on
+    fn record(&mut self, result: Result<bool, MinigrepError>) -> Result<(), MinigrepError> {
+        match result {
+            Ok(matched) => self.matched |= matched,
+            Err(e @ MinigrepError::Output(_)) => return Err(e),
+            Err(e) => {
+                warn(&e);
+                self.had_errors = true;
+            }
+        }
+        Ok(())
+    }
+}
+
+// searches a single file (or stdin) and prints whatever the output mode asks for
+// returns whether anything was selected, as far as the exit status is concerned
+fn search_path<M: Matcher, W: Write>(
+    config: &Config,
+    searcher: &Searcher<M>,
+    path: &Path,
+    name: &str,
+    with_path: bool,
+    printer: &mut Printer<W>,
+) -> Result<bool, SearchError> {
+    printer.begin_file(name, with_path);
+
+    // big plain files are memory-mapped and searched as a single slice, see search_mapped
+    // everything else is searched a line at a time through a buffered reader, so memory use stays flat no matter how big the input is
+    // input::open hides whether that reader decompresses or decodes, so every output mode works on .gz and UTF-16 files too
+    let mapped = if can_map(config) {
+        input::map(path, config)?
     } else {
-        search(&config.query, &contents)
+        None
+    };
+    let count = match mapped {
+        Some(map) => search_mapped(config, searcher, &map, printer)?,
+        None => {
+            let input = input::open(path, config)?;
+            search_reader(config, searcher, input.reader, printer)?
+        }
     };
 
-    for line in results {
-        println!("{line}");
+    // the line-oriented mode has printed as it went; the others only know what to print once the file is done
+    printer.end_file();
+    let matched = match config.output_mode {
+        OutputMode::Lines | OutputMode::Json => count > 0,
+        OutputMode::Count => {
+            printer.count(count).map_err(SearchError::Output)?;
+            count > 0
+        }
+        OutputMode::FilesWithMatches if count > 0 => {
+            printer.file_name(name).map_err(SearchError::Output)?;
+            true
+        }
+        OutputMode::FilesWithoutMatch if count == 0 => {
+            printer.file_name(name).map_err(SearchError::Output)?;
+            true
+        }
+        OutputMode::FilesWithMatches | OutputMode::FilesWithoutMatch => false,
+    };
+
+    Ok(matched)
+}
+
+// failures while searching one input, before it's known which file they're about
+// Input ones skip that input with a warning, Output ones (e.g. stdout was closed) end the run
+#[derive(Debug)]
+enum SearchError {
+    Input(io::Error),
+    Output(io::Error),
+}
+
+impl SearchError {
+    fn at(self, path: &Path) -> MinigrepError {
+        match self {
+            SearchError::Input(source) => MinigrepError::Io {
+                path: path.to_path_buf(),
+                source,
+            },
+            SearchError::Output(source) => MinigrepError::Output(source),
+        }
+    }
+}
+
+impl From<io::Error> for SearchError {
+    // anything the searcher itself runs into is a problem with the input
+    fn from(e: io::Error) -> SearchError {
+        SearchError::Input(e)
     }
+}
 
-    // this is an idiomatic way to indicate that this func is called for its side effects only; it doesn't return a value you need
-    Ok(())
+// searches any buffered reader line by line, so only the current line (plus any -B context) is ever held in memory
+// returns how many lines were selected, i.e. matched (or didn't, with -v)
+fn search_reader<M: Matcher, R: BufRead, W: Write>(
+    config: &Config,
+    searcher: &Searcher<M>,
+    mut reader: R,
+    printer: &mut Printer<W>,
+) -> Result<usize, SearchError> {
+    // like grep, treat the input as binary if a NUL byte shows up in the first block read
+    // binary files are still searched, but printing their lines is left to --text
+    let binary = !config.text && reader.fill_buf()?.contains(&0);
+    let mut sink = LineSink::new(config, searcher, printer, binary);
+
+    searcher.search_with(reader, |found, is_selected| sink.line(found, is_selected))?;
+
+    Ok(sink.selected)
 }
 
-pub struct Config {
-    pub query: String,
-    pub file_path: String,
-    pub ignore_case: bool,
+// the same for a file that's been memory-mapped, which can skip every line that doesn't contain the query
+// only the selected lines are looked at, so it's only used when nothing else needs printing, see can_map
+fn search_mapped<M: Matcher, W: Write>(
+    config: &Config,
+    searcher: &Searcher<M>,
+    haystack: &[u8],
+    printer: &mut Printer<W>,
+) -> Result<usize, SearchError> {
+    // the same first block a BufReader would have looked at
+    let first_block = &haystack[..haystack.len().min(8 * 1024)];
+    let binary = !config.text && first_block.contains(&0);
+    let mut sink = LineSink::new(config, searcher, printer, binary);
+
+    searcher.search_slice(haystack, |found, is_selected| sink.line(found, is_selected))?;
+
+    Ok(sink.selected)
 }
 
-/* The first step: extract the parsing logic from the main to a func */
-// fn parse_config(args: &[String]) -> Config {
-//     // clone makes it straightforward(managing the lifetimes of the refs isn't necessary) but takes a bit more time to process at runtime so performance gets worse
-//     // it's a trade-off and which one to choose depends on things like the scale of your project
-//     let query = args[1].clone();
-//     let file_path = args[2].clone();
+// the mapped fast path hands over selected lines only: fine for counts and file names, and for plain lines
+// unless context or the per-line stats of --json need the others
+fn can_map(config: &Config) -> bool {
+    let context = config.print_options.before_context > 0 || config.print_options.after_context > 0;
+    config.output_mode != OutputMode::Json && !context
+}
 
-//     Config { query, file_path }
-// }
+// what happens to each line a search turns up, whichever way the input is read
+struct LineSink<'a, M, W: Write> {
+    config: &'a Config,
+    searcher: &'a Searcher<M>,
+    printer: &'a mut Printer<W>,
+    binary: bool,
+    // how many lines were selected so far
+    selected: usize,
+}
 
-impl Config {
-    pub fn build(
-        /* args: &[String] */ mut args: impl Iterator<Item = String>,
-    ) -> Result<Config, &'static str> {
-        // the fist arg returned from env::args is the name of the program, so go one step further in advance
-        args.next();
+impl<'a, M: Matcher, W: Write> LineSink<'a, M, W> {
+    fn new(
+        config: &'a Config,
+        searcher: &'a Searcher<M>,
+        printer: &'a mut Printer<W>,
+        binary: bool,
+    ) -> LineSink<'a, M, W> {
+        LineSink {
+            config,
+            searcher,
+            printer,
+            binary,
+            selected: 0,
+        }
+    }
 
-        let query = match args.next() {
-            Some(arg) => arg,
-            None => return Err("Didn't get a query string"),
-        };
+    // returns whether the search should go on
+    fn line(&mut self, found: Match<'_>, is_selected: bool) -> Result<bool, SearchError> {
+        if is_selected {
+            self.selected += 1;
+        }
 
-        let file_path = match args.next() {
-            Some(arg) => arg,
-            None => return Err("Didn't get a file path"),
-        };
+        match self.config.output_mode {
+            // counts and file names are the same for binary files, so only the line-printing modes need this
+            OutputMode::Lines | OutputMode::Json if self.binary && is_selected => {
+                self.printer.binary_match().map_err(SearchError::Output)?;
+                return Ok(false);
+            }
+            OutputMode::Lines | OutputMode::Json if self.binary => {}
+            OutputMode::Lines => self
+                .printer
+                .line(
+                    found.line_number,
+                    found.line,
+                    is_selected,
+                    self.searcher.matcher(),
+                    &self.config.patterns,
+                )
+                .map_err(SearchError::Output)?,
+            OutputMode::Json => self
+                .printer
+                .json_line(
+                    found,
+                    is_selected,
+                    self.searcher.matcher(),
+                    &self.config.patterns,
+                )
+                .map_err(SearchError::Output)?,
+            // one hit is enough to decide either way, so there's no need to read the rest
+            OutputMode::FilesWithMatches | OutputMode::FilesWithoutMatch if is_selected => {
+                return Ok(false)
+            }
+            _ => {}
+        }
 
-        let ignore_case = env::var("IGNORE_CASE").is_ok();
+        Ok(true)
+    }
+}
 
-        // if args.len() < 3 {
-        //     // panic!("not enough arguments");
-        //     return Err("not enough arguments");
-        // }
-        // let query = args[1].clone();
-        // let file_path = args[2].clone();
-        // let ignore_case = env::var("IGNORE_CASE").is_ok();
+fn is_stdin(path: &Path) -> bool {
+    path == Path::new("-")
+}
 
-        Ok(Config {
-            query,
-            file_path,
-            ignore_case,
-        })
+fn display_name(path: &Path) -> String {
+    if is_stdin(path) {
+        "(standard input)".to_string()
+    } else {
+        path.display().to_string()
     }
 }
+
+// the errors a run carries on from already name their file
+fn warn(err: &MinigrepError) {
+    eprintln!("minigrep: {err}");
+}
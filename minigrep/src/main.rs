@@ -1,7 +1,8 @@
 use std::env;
+use std::io::{self, Write};
 use std::process;
 
-use minigrep::Config;
+use minigrep::{Command, Config, MinigrepError};
 
 fn main() {
     // to also cope with invalid Unicode, use args_os instead
@@ -15,24 +16,56 @@ fn main() {
     // let config = parse_config(&args);
 
     // unwrap_or_else is similar to unwrap when the status is Ok, but it allows you to customize what to do when it's Err with an anonymous func that defines it accepted in its arg
-    let config = Config::build(/* &args */ env::args()).unwrap_or_else(|err| {
-        eprintln!("Problem parsing arguments: {err}");
-        process::exit(1);
-    });
+    // it's a match now, since an Ok can also be --help or --version rather than something to search
+    let config = match Config::build(/* &args */ env::args()) {
+        Ok(Command::Run(config)) => *config,
+        // what the user asked for, so they go to stdout
+        Ok(Command::Help) => print_and_exit(&minigrep::help()),
+        Ok(Command::Version) => print_and_exit(&format!("minigrep {}", env!("CARGO_PKG_VERSION"))),
+        Err(err) => {
+            eprintln!("Problem parsing arguments: {err}");
+            eprintln!("Try 'minigrep --help' for more information.");
+            // like grep, 2 and up mean trouble; 1 is kept for "nothing matched"
+            process::exit(err.exit_code());
+        }
+    };
 
-    println!("query: {}", config.query);
-    println!("file_path: {}", config.file_path);
+    // these would get mixed into the search results, which scripts read line by line
+    // println!("query: {}", config.query);
+    // println!("file_path: {}", config.file_path);
     // println!("query: {}", query);
     // println!("file_path: {}", file_path);
     // dbg!(args);
 
     // use if let rather than unwrap_or_else like Config::build does because here you should only check whether run returns an Err val which means you don't need a val if Ok
-    if let Err(e) = minigrep::run(config) {
-        eprintln!("Application error: {e}");
-        process::exit(1);
-    };
+    // if let Err(e) = minigrep::run(config) {
+    //     eprintln!("Application error: {e}");
+    //     process::exit(1);
+    // };
+    // now run's Ok val matters too, since whether anything matched decides the exit status
+    match minigrep::run(config) {
+        Ok(summary) => process::exit(summary.exit_code()),
+        // whoever was reading stopped early (e.g. minigrep ... | head), which is no reason to complain
+        Err(MinigrepError::Output(e)) if e.kind() == io::ErrorKind::BrokenPipe => process::exit(0),
+        Err(e) => {
+            eprintln!("Application error: {e}");
+            process::exit(e.exit_code());
+        }
+    }
     // let contents =
     //     fs::read_to_string(config.file_path).expect("Should have been able to read the file!");
     // let contents = fs::read_to_string(file_path).expect("Should have been able to read the file!");
     // println!("With text:\n{contents}");
 }
+
+// for --help and --version; println! would panic if whoever reads stops early (minigrep --help | head)
+fn print_and_exit(text: &str) -> ! {
+    match writeln!(io::stdout(), "{text}") {
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => {
+            let err = MinigrepError::Output(e);
+            eprintln!("Application error: {err}");
+            process::exit(err.exit_code());
+        }
+        _ => process::exit(0),
+    }
+}
@@ -0,0 +1,722 @@
+use std::ops::Range;
+
+use aho_corasick::{AhoCorasick, Anchored, BuildError, Input, MatchKind, StartKind};
+use regex::Regex;
+
+/// Decides whether, and where, a line matches.
+///
+/// Implement it to plug a new kind of matching into a [`Searcher`](crate::Searcher).
+/// Only `find_at` is required; the rest is built on top of it.
+pub trait Matcher {
+    /// The byte range of the first match in `line` that starts at or after `start`.
+    ///
+    /// Ranges always point into `line` itself, so they can be used to slice it even when case was ignored.
+    fn find_at(&self, line: &str, start: usize) -> Option<Range<usize>>;
+
+    fn is_match(&self, line: &str) -> bool {
+        self.find_at(line, 0).is_some()
+    }
+
+    /// Every non-overlapping, non-empty match in `line`, left to right.
+    fn find_all(&self, line: &str) -> Vec<Range<usize>> {
+        let mut found = Vec::new();
+        let mut start = 0;
+
+        while let Some(range) = self.find_at(line, start) {
+            if range.is_empty() {
+                // an empty match (e.g. the regex ^) has nothing in it, so step over one char and keep looking
+                match line[range.end..].chars().next() {
+                    Some(c) => start = range.end + c.len_utf8(),
+                    None => break,
+                }
+            } else {
+                start = range.end;
+                found.push(range);
+            }
+        }
+
+        found
+    }
+
+    /// Appends what the match at `range` in `line` gets replaced with to `dst`.
+    ///
+    /// Matchers with capture groups expand `$1`, `${name}` and the like in `replacement`;
+    /// the rest append it as is.
+    fn replace_into(&self, line: &str, range: Range<usize>, replacement: &str, dst: &mut String) {
+        let _ = (line, range);
+        dst.push_str(replacement);
+    }
+
+    /// Which pattern, counting from 0, the match at `range` in `line` came from.
+    ///
+    /// Only matchers built from several patterns have anything to tell apart; the rest always say 0.
+    fn pattern_of(&self, line: &str, range: Range<usize>) -> usize {
+        let _ = (line, range);
+        0
+    }
+
+    /// A string that every match contains, if there is one.
+    ///
+    /// It lets [`Searcher::search_slice`](crate::Searcher::search_slice) jump straight to the lines
+    /// containing it instead of trying every line.
+    fn required_literal(&self) -> Option<&str> {
+        None
+    }
+}
+
+// so that boxed and borrowed matchers, e.g. the Box<dyn Matcher> run builds from its config, work anywhere a matcher does
+impl<M: Matcher + ?Sized> Matcher for Box<M> {
+    fn find_at(&self, line: &str, start: usize) -> Option<Range<usize>> {
+        (**self).find_at(line, start)
+    }
+
+    fn is_match(&self, line: &str) -> bool {
+        (**self).is_match(line)
+    }
+
+    fn find_all(&self, line: &str) -> Vec<Range<usize>> {
+        (**self).find_all(line)
+    }
+
+    fn replace_into(&self, line: &str, range: Range<usize>, replacement: &str, dst: &mut String) {
+        (**self).replace_into(line, range, replacement, dst)
+    }
+
+    fn pattern_of(&self, line: &str, range: Range<usize>) -> usize {
+        (**self).pattern_of(line, range)
+    }
+
+    fn required_literal(&self) -> Option<&str> {
+        (**self).required_literal()
+    }
+}
+
+impl<M: Matcher + ?Sized> Matcher for &M {
+    fn find_at(&self, line: &str, start: usize) -> Option<Range<usize>> {
+        (**self).find_at(line, start)
+    }
+
+    fn is_match(&self, line: &str) -> bool {
+        (**self).is_match(line)
+    }
+
+    fn find_all(&self, line: &str) -> Vec<Range<usize>> {
+        (**self).find_all(line)
+    }
+
+    fn replace_into(&self, line: &str, range: Range<usize>, replacement: &str, dst: &mut String) {
+        (**self).replace_into(line, range, replacement, dst)
+    }
+
+    fn pattern_of(&self, line: &str, range: Range<usize>) -> usize {
+        (**self).pattern_of(line, range)
+    }
+
+    fn required_literal(&self) -> Option<&str> {
+        (**self).required_literal()
+    }
+}
+
+/// Matches the query exactly as written.
+#[derive(Debug, Clone)]
+pub struct LiteralMatcher {
+    query: String,
+}
+
+impl LiteralMatcher {
+    pub fn new(query: &str) -> LiteralMatcher {
+        LiteralMatcher {
+            query: query.to_string(),
+        }
+    }
+}
+
+impl Matcher for LiteralMatcher {
+    fn find_at(&self, line: &str, start: usize) -> Option<Range<usize>> {
+        line[start..]
+            .find(&self.query)
+            .map(|i| start + i..start + i + self.query.len())
+    }
+
+    fn is_match(&self, line: &str) -> bool {
+        line.contains(&self.query)
+    }
+
+    fn required_literal(&self) -> Option<&str> {
+        Some(&self.query)
+    }
+}
+
+/// Matches the query regardless of case, using full Unicode case folding (ß matches SS, Σ matches ς).
+#[derive(Debug, Clone)]
+pub struct CaseInsensitiveMatcher {
+    // the queries already case-folded, see fold; just the one unless built with any_of
+    folded: Vec<String>,
+    // any_of's queries, for scanning a folded line for all of them at once; None for a single query
+    automaton: Option<AhoCorasick>,
+    // the length of the longest folded query, which bounds how far past a match a better one can end
+    longest: usize,
+}
+
+impl CaseInsensitiveMatcher {
+    pub fn new(query: &str) -> CaseInsensitiveMatcher {
+        CaseInsensitiveMatcher {
+            folded: vec![query.chars().flat_map(fold).collect()],
+            automaton: None,
+            longest: 0,
+        }
+    }
+
+    /// Matches wherever any of `queries` does, regardless of case, using the Aho–Corasick algorithm
+    /// so each line is scanned once no matter how many queries there are.
+    ///
+    /// Where several match at the same spot, the longest one wins, as with [`MultiLiteralMatcher`].
+    pub fn any_of<Q: AsRef<str>>(queries: &[Q]) -> Result<CaseInsensitiveMatcher, BuildError> {
+        let folded: Vec<String> = queries
+            .iter()
+            .map(|query| query.as_ref().chars().flat_map(fold).collect())
+            .collect();
+        // Standard, since only its overlapping search turns up every candidate, see leftmost_longest
+        // the folded queries are lowercase already, so ignoring ASCII case only matters for ASCII lines, see find_among
+        let automaton = AhoCorasick::builder()
+            .ascii_case_insensitive(true)
+            .build(&folded)?;
+
+        Ok(CaseInsensitiveMatcher {
+            longest: folded.iter().map(String::len).max().unwrap_or(0),
+            folded,
+            automaton: Some(automaton),
+        })
+    }
+
+    // any_of's find_at
+    fn find_among(
+        &self,
+        automaton: &AhoCorasick,
+        line: &str,
+        start: usize,
+    ) -> Option<Range<usize>> {
+        // ASCII folds to its own lowercase, byte for byte, and the automaton ignores ASCII case,
+        // so an ASCII line (most of them, in most files) can be searched as it is
+        if line[start..].is_ascii() {
+            let found = automaton.find_overlapping_iter(Input::new(line).range(start..));
+            return self.leftmost_longest(found.map(|m| m.range()), Some);
+        }
+
+        // anything else gets a folded copy, which costs an allocation, but is still walked only once for every query
+        // origin says where in line each byte of the copy came from, for the bytes that begin a char's fold;
+        // a match that starts or ends anywhere else would split a char (the s of the ss that ß folds to)
+        let mut folded = String::new();
+        let mut origin = Vec::new();
+        for (i, c) in line[start..].char_indices() {
+            origin.push(Some(start + i));
+            folded.extend(fold(c));
+            origin.resize(folded.len(), None);
+        }
+        origin.push(Some(line.len()));
+
+        let found = automaton.find_overlapping_iter(&folded);
+        self.leftmost_longest(found.map(|m| m.range()), |i| origin[i])
+    }
+
+    // picks the leftmost of the overlapping matches found, and the longest of those,
+    // skipping the ones origin can't map back to char boundaries of the line
+    fn leftmost_longest(
+        &self,
+        found: impl Iterator<Item = Range<usize>>,
+        origin: impl Fn(usize) -> Option<usize>,
+    ) -> Option<Range<usize>> {
+        let mut best: Option<(usize, Range<usize>)> = None;
+        for range in found {
+            // overlapping matches come out ordered by where they end, so once they end further than the longest query
+            // past the best start so far, none of the rest can start any earlier
+            if best
+                .as_ref()
+                .is_some_and(|(from, _)| range.end > from + self.longest)
+            {
+                break;
+            }
+            let (Some(from), Some(to)) = (origin(range.start), origin(range.end)) else {
+                continue;
+            };
+            let better = best.as_ref().is_none_or(|(best_from, best)| {
+                range.start < *best_from || (range.start == *best_from && to > best.end)
+            });
+            if better {
+                best = Some((range.start, from..to));
+            }
+        }
+
+        best.map(|(_, range)| range)
+    }
+}
+
+impl Matcher for CaseInsensitiveMatcher {
+    // a single query gets no folded copy of the line: chars are folded one by one as they're compared
+    fn find_at(&self, line: &str, start: usize) -> Option<Range<usize>> {
+        if let Some(automaton) = &self.automaton {
+            return self.find_among(automaton, line, start);
+        }
+
+        line[start..]
+            .char_indices()
+            .map(|(i, _)| start + i)
+            .chain([line.len()])
+            .find_map(|from| match_folded(&self.folded[0], line, from).map(|end| from..end))
+    }
+
+    fn pattern_of(&self, line: &str, range: Range<usize>) -> usize {
+        self.folded
+            .iter()
+            .position(|query| match_folded(query, line, range.start) == Some(range.end))
+            .unwrap_or(0)
+    }
+}
+
+/// Matches a regular expression; build the `Regex` with `RegexBuilder` to ignore case.
+#[derive(Debug, Clone)]
+pub struct RegexMatcher {
+    regex: Regex,
+    // the patterns any_of was built from, to tell which one a match came from; empty otherwise
+    alternatives: Vec<Regex>,
+}
+
+impl RegexMatcher {
+    pub fn new(pattern: &str) -> Result<RegexMatcher, regex::Error> {
+        Regex::new(pattern).map(RegexMatcher::from)
+    }
+
+    /// Matches wherever any of `regexes` does, in a single pass over the line.
+    ///
+    /// Where several match at the same spot, the first one given wins.
+    /// With no regexes at all, nothing matches.
+    pub fn any_of(regexes: Vec<Regex>) -> Result<RegexMatcher, regex::Error> {
+        let alternation: Vec<String> = regexes
+            .iter()
+            .map(|regex| format!("(?:{})", regex.as_str()))
+            .collect();
+        // joining nothing would give the empty regex, which matches everywhere
+        // a class of no chars at all is the regex that matches nowhere
+        let alternation = if alternation.is_empty() {
+            r"[^\x00-\x{10FFFF}]".to_string()
+        } else {
+            alternation.join("|")
+        };
+
+        Ok(RegexMatcher {
+            regex: Regex::new(&alternation)?,
+            alternatives: regexes,
+        })
+    }
+}
+
+impl From<Regex> for RegexMatcher {
+    fn from(regex: Regex) -> RegexMatcher {
+        RegexMatcher {
+            regex,
+            alternatives: Vec::new(),
+        }
+    }
+}
+
+impl Matcher for RegexMatcher {
+    fn find_at(&self, line: &str, start: usize) -> Option<Range<usize>> {
+        self.regex.find_at(line, start).map(|m| m.range())
+    }
+
+    fn is_match(&self, line: &str) -> bool {
+        self.regex.is_match(line)
+    }
+
+    // searching again from where the match starts finds that same match, this time with its groups
+    // for any_of that's done with the pattern it came from, since in the combined regex
+    // the second pattern's $1 is really $2
+    fn replace_into(&self, line: &str, range: Range<usize>, replacement: &str, dst: &mut String) {
+        let regex = if self.alternatives.is_empty() {
+            &self.regex
+        } else {
+            &self.alternatives[self.pattern_of(line, range.clone())]
+        };
+
+        match regex.captures_at(line, range.start) {
+            Some(caps) if caps.get(0).map(|m| m.range()) == Some(range) => {
+                caps.expand(replacement, dst)
+            }
+            _ => dst.push_str(replacement),
+        }
+    }
+
+    // the combined regex can't say which branch matched, so ask each pattern in turn which one matches right there
+    fn pattern_of(&self, line: &str, range: Range<usize>) -> usize {
+        self.alternatives
+            .iter()
+            .position(|regex| {
+                regex.find_at(line, range.start).map(|m| m.range()) == Some(range.clone())
+            })
+            .unwrap_or(0)
+    }
+}
+
+/// Matches any of several literal strings at once, using the Aho–Corasick algorithm
+/// so the line is scanned once no matter how many patterns there are.
+///
+/// Where several match at the same spot, the longest one wins.
+#[derive(Debug, Clone)]
+pub struct MultiLiteralMatcher {
+    automaton: AhoCorasick,
+}
+
+impl MultiLiteralMatcher {
+    pub fn new<P: AsRef<str>>(patterns: &[P]) -> Result<MultiLiteralMatcher, BuildError> {
+        let automaton = AhoCorasick::builder()
+            .match_kind(MatchKind::LeftmostLongest)
+            // pattern_of searches anchored at the match it's asked about
+            .start_kind(StartKind::Both)
+            .build(patterns.iter().map(AsRef::as_ref))?;
+
+        Ok(MultiLiteralMatcher { automaton })
+    }
+}
+
+impl Matcher for MultiLiteralMatcher {
+    fn find_at(&self, line: &str, start: usize) -> Option<Range<usize>> {
+        self.automaton
+            .find(Input::new(line).range(start..))
+            .map(|m| m.range())
+    }
+
+    fn pattern_of(&self, line: &str, range: Range<usize>) -> usize {
+        let input = Input::new(line).range(range).anchored(Anchored::Yes);
+        self.automaton
+            .find(input)
+            .map_or(0, |m| m.pattern().as_usize())
+    }
+}
+
+/// Wraps another matcher and only keeps its matches that form whole words,
+/// i.e. aren't directly preceded or followed by a Unicode letter, digit or `_`.
+#[derive(Debug, Clone)]
+pub struct WordMatcher<M> {
+    inner: M,
+}
+
+impl<M: Matcher> WordMatcher<M> {
+    pub fn new(inner: M) -> WordMatcher<M> {
+        WordMatcher { inner }
+    }
+}
+
+impl<M: Matcher> Matcher for WordMatcher<M> {
+    fn find_at(&self, line: &str, start: usize) -> Option<Range<usize>> {
+        let mut from = start;
+
+        loop {
+            let found = self.inner.find_at(line, from)?;
+
+            let before = line[..found.start].chars().next_back();
+            let after = line[found.end..].chars().next();
+            if !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char) {
+                return Some(found);
+            }
+
+            // "rust" in "trust rust" first turns up inside trust, so look again from just past where that candidate began
+            from = found.start + line[found.start..].chars().next()?.len_utf8();
+        }
+    }
+
+    fn replace_into(&self, line: &str, range: Range<usize>, replacement: &str, dst: &mut String) {
+        self.inner.replace_into(line, range, replacement, dst)
+    }
+
+    fn pattern_of(&self, line: &str, range: Range<usize>) -> usize {
+        self.inner.pattern_of(line, range)
+    }
+
+    // a whole-word match is still a match of the inner matcher
+    fn required_literal(&self) -> Option<&str> {
+        self.inner.required_literal()
+    }
+}
+
+/// Matches any part of the line that's within `max_distance` edits of the query,
+/// an edit being one char inserted, removed or swapped for another.
+///
+/// With a distance of 1, `"seperate"` matches `"separate"` and `"color"` matches `"colour"`.
+#[derive(Debug, Clone)]
+pub struct FuzzyMatcher {
+    query: Vec<char>,
+    max_distance: usize,
+    ignore_case: bool,
+}
+
+impl FuzzyMatcher {
+    pub fn new(query: &str, max_distance: usize) -> FuzzyMatcher {
+        FuzzyMatcher {
+            query: query.chars().collect(),
+            max_distance,
+            ignore_case: false,
+        }
+    }
+
+    /// Compares chars the way [`CaseInsensitiveMatcher`] does.
+    pub fn ignore_case(mut self, yes: bool) -> FuzzyMatcher {
+        self.ignore_case = yes;
+        self
+    }
+
+    fn same(&self, a: char, b: char) -> bool {
+        a == b || (self.ignore_case && fold(a).eq(fold(b)))
+    }
+}
+
+impl Matcher for FuzzyMatcher {
+    // Sellers' algorithm: edit distance where the match may start anywhere in the line for free
+    // each cell keeps where in the line its best alignment started, so the match's start falls out at the end
+    // the first end within max_distance is taken, then pushed on for as long as that brings the distance down,
+    // so that "hello" matches all of hello rather than stopping at hell
+    fn find_at(&self, line: &str, start: usize) -> Option<Range<usize>> {
+        // column[i]: the distance between query[..i] and the best-matching text ending at the current position, and its start
+        let mut column: Vec<(usize, usize)> = (0..=self.query.len()).map(|i| (i, start)).collect();
+        let within = |(distance, from): (usize, usize), to: usize| {
+            (distance <= self.max_distance).then_some((distance, from..to))
+        };
+        let mut best = within(column[self.query.len()], start);
+
+        for (i, c) in line[start..].char_indices() {
+            let end = start + i + c.len_utf8();
+            let mut diagonal = column[0];
+            column[0] = (0, end);
+
+            for (q, &wanted) in self.query.iter().enumerate() {
+                let swap = (diagonal.0 + usize::from(!self.same(wanted, c)), diagonal.1);
+                let insert = (column[q + 1].0 + 1, column[q + 1].1);
+                let remove = (column[q].0 + 1, column[q].1);
+                diagonal = column[q + 1];
+                // on a tie the earlier start wins, so "fog" matches all of frog rather than just og,
+                // which -w would then throw away for not being a whole word
+                column[q + 1] = [swap, insert, remove].into_iter().min().unwrap();
+            }
+
+            let here = within(column[self.query.len()], end);
+            match (&best, here) {
+                (Some((distance, _)), Some((closer, range))) if closer < *distance => {
+                    best = Some((closer, range))
+                }
+                (Some(_), _) => break,
+                (None, here) => best = here,
+            }
+        }
+
+        best.map(|(_, range)| range)
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// full Unicode case folding of a single char, so that e.g. ß, ẞ and SS all compare equal
+// lowercase -> uppercase -> lowercase takes care of the chars whose fold is more than one char (ß -> ss, ﬁ -> fi)
+// and of the ones with several lowercase forms (ς and σ both fold to σ)
+// İ gets the Turkish treatment and folds to a plain i rather than i + a combining dot, and dotless ı folds to i via I,
+// so Turkish text matches the way people type it
+fn fold(c: char) -> impl Iterator<Item = char> {
+    let dotted_capital_i = c == 'İ';
+
+    c.to_lowercase()
+        .flat_map(char::to_uppercase)
+        .flat_map(char::to_lowercase)
+        .filter(move |&f| !(dotted_capital_i && f == '\u{307}'))
+}
+
+// folding the whole line up front would cost an allocation per line, and it would also shift byte offsets
+// whenever a char folds to something of a different length (ß is 2 bytes, ss is 2 chars)
+// so instead each char of the line is folded as it's compared, and a match has to start and end on a char boundary of the original line
+// returns where the match of folded_query starting right at from ends, if there is one
+fn match_folded(folded_query: &str, line: &str, from: usize) -> Option<usize> {
+    if folded_query.is_empty() {
+        return Some(from);
+    }
+
+    let mut wanted = folded_query.chars();
+
+    for (j, c) in line[from..].char_indices() {
+        for folded in fold(c) {
+            if wanted.next() != Some(folded) {
+                return None;
+            }
+        }
+        if wanted.as_str().is_empty() {
+            return Some(from + j + c.len_utf8());
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn case_insensitive_ranges_point_into_the_original_line() {
+        let matcher = CaseInsensitiveMatcher::new("rust");
+        let line = "İRUST and rust";
+
+        assert_eq!(Some(2..6), matcher.find_at(line, 0));
+        assert_eq!(Some(11..15), matcher.find_at(line, 6));
+        assert_eq!(None, matcher.find_at(line, 12));
+    }
+
+    #[test]
+    fn folds_beyond_lowercasing() {
+        let matcher = CaseInsensitiveMatcher::new("STRASSE");
+        assert_eq!(Some(4..11), matcher.find_at("Die Straße", 0));
+        assert!(matcher.is_match("GROẞE STRAẞE"));
+        assert!(!matcher.is_match("Strase"));
+
+        let matcher = CaseInsensitiveMatcher::new("σίσυφος");
+        assert!(matcher.is_match("ΣΊΣΥΦΟΣ"));
+        assert!(matcher.is_match("σίσυφος"));
+
+        let matcher = CaseInsensitiveMatcher::new("istanbul");
+        assert_eq!(Some(0..9), matcher.find_at("İSTANBUL", 0));
+        assert!(matcher.is_match("ıstanbul"));
+    }
+
+    #[test]
+    fn case_insensitive_any_of_folds_every_query() {
+        let matcher = CaseInsensitiveMatcher::any_of(&["nope", "STRASSE", "stras"]).unwrap();
+
+        // stras matches at the same spot, but the longer query wins
+        assert_eq!(Some(4..11), matcher.find_at("Die Straße", 0));
+        assert_eq!(1, matcher.pattern_of("Die Straße", 4..11));
+        assert_eq!(vec![0..4, 5..10], matcher.find_all("NOPE strasz"));
+        assert_eq!(2, matcher.pattern_of("NOPE strasz", 5..10));
+
+        // neither may take only half of the ss that ß folds to
+        let matcher = CaseInsensitiveMatcher::any_of(&["s", "sx"]).unwrap();
+        assert_eq!(None, matcher.find_at("ßx", 0));
+        assert_eq!(Some(3..4), matcher.find_at("ßxs", 0));
+
+        // a pattern set as big as a -f file makes them, found the same as one query at a time would
+        let queries: Vec<String> = (0..500).map(|i| format!("Wörd{i}")).collect();
+        let matcher = CaseInsensitiveMatcher::any_of(&queries).unwrap();
+        let line = "wÖRD7 and WÖRD499, then wörd500, which only wörd50 covers";
+        assert_eq!(vec![0..6, 11..19, 26..33, 47..54], matcher.find_all(line));
+        assert_eq!(499, matcher.pattern_of(line, 11..19));
+        assert_eq!(50, matcher.pattern_of(line, 26..33));
+    }
+
+    #[test]
+    fn matches_end_on_char_boundaries() {
+        // the query can't stop halfway through the ss that ß folds to
+        let matcher = CaseInsensitiveMatcher::new("s");
+        assert!(!matcher.is_match("ß"));
+        assert!(CaseInsensitiveMatcher::new("ss").is_match("ß"));
+    }
+
+    #[test]
+    fn word_matcher_skips_partial_words() {
+        let matcher = WordMatcher::new(LiteralMatcher::new("rust"));
+
+        assert_eq!(Some(6..10), matcher.find_at("trust rust", 0));
+        assert_eq!(vec![13..17], matcher.find_all("trust rust_y rust"));
+        assert!(!matcher.is_match("Rustacean"));
+
+        let matcher = WordMatcher::new(CaseInsensitiveMatcher::new("straße"));
+        assert_eq!(vec![4..11], matcher.find_all("die STRASSE, Straßenbahn"));
+    }
+
+    #[test]
+    fn boxed_and_borrowed_matchers_keep_overridden_methods() {
+        // reports its matches back to front, which the default find_all never would
+        struct Backwards;
+        impl Matcher for Backwards {
+            fn find_at(&self, line: &str, start: usize) -> Option<Range<usize>> {
+                line[start..].find('x').map(|i| start + i..start + i + 1)
+            }
+
+            fn find_all(&self, line: &str) -> Vec<Range<usize>> {
+                let mut found = RegexMatcher::new("x").unwrap().find_all(line);
+                found.reverse();
+                found
+            }
+        }
+        fn find_all_through<M: Matcher>(matcher: M, line: &str) -> Vec<Range<usize>> {
+            matcher.find_all(line)
+        }
+
+        let boxed: Box<dyn Matcher> = Box::new(Backwards);
+        assert_eq!(vec![4..5, 0..1], boxed.find_all("x y x"));
+        assert_eq!(vec![4..5, 0..1], find_all_through(&Backwards, "x y x"));
+    }
+
+    #[test]
+    fn find_all_skips_empty_matches() {
+        let matcher = RegexMatcher::new("o*").unwrap();
+
+        assert_eq!(vec![1..3, 5..6], matcher.find_all("fool o"));
+    }
+
+    #[test]
+    fn regex_replacements_expand_capture_groups() {
+        let matcher = RegexMatcher::new(r"(?<first>\w+) (\w+)").unwrap();
+        let line = "safe fast";
+        let mut replaced = String::new();
+        matcher.replace_into(line, 0..9, "$2 ${first}", &mut replaced);
+        assert_eq!("fast safe", replaced);
+
+        // each pattern of any_of keeps its own group numbers
+        let regexes = ["(foo)", "(bar)"].map(|p| Regex::new(p).unwrap());
+        let matcher = RegexMatcher::any_of(regexes.to_vec()).unwrap();
+        let mut replaced = String::new();
+        for range in matcher.find_all("foo bar") {
+            matcher.replace_into("foo bar", range, "[$1]", &mut replaced);
+        }
+        assert_eq!("[foo][bar]", replaced);
+
+        // no groups to expand for the other matchers
+        let mut replaced = String::new();
+        LiteralMatcher::new("safe").replace_into(line, 0..4, "$1", &mut replaced);
+        assert_eq!("$1", replaced);
+    }
+
+    #[test]
+    fn multiple_patterns_report_which_one_matched() {
+        let matcher = MultiLiteralMatcher::new(&["fast", "safe", "saf"]).unwrap();
+        let line = "safe, fast, productive.";
+
+        assert_eq!(vec![0..4, 6..10], matcher.find_all(line));
+        assert_eq!(1, matcher.pattern_of(line, 0..4));
+        assert_eq!(0, matcher.pattern_of(line, 6..10));
+        assert!(!MultiLiteralMatcher::new::<&str>(&[])
+            .unwrap()
+            .is_match(line));
+        assert!(!RegexMatcher::any_of(Vec::new()).unwrap().is_match(line));
+        assert!(!RegexMatcher::any_of(Vec::new()).unwrap().is_match(""));
+
+        let regexes = ["^Pick", r"\bthree"].map(|p| Regex::new(p).unwrap());
+        let matcher = RegexMatcher::any_of(regexes.to_vec()).unwrap();
+        assert_eq!(vec![0..4, 5..10], matcher.find_all("Pick three."));
+        assert_eq!(1, matcher.pattern_of("Pick three.", 5..10));
+    }
+
+    #[test]
+    fn fuzzy_matches_within_the_edit_distance() {
+        let matcher = FuzzyMatcher::new("separate", 1);
+        assert_eq!(Some(4..12), matcher.find_at("two seperate ones", 0));
+        assert_eq!(Some(0..8), matcher.find_at("separate", 0));
+        assert!(matcher.is_match("seprate"));
+        // swapping two letters is two edits
+        assert!(!matcher.is_match("spearate"));
+        assert!(!matcher.is_match("desperate"));
+
+        let matcher = FuzzyMatcher::new("colour", 1);
+        assert_eq!(vec![4..9, 14..20], matcher.find_all("the color and colour"));
+
+        let matcher = FuzzyMatcher::new("STRASSE", 1).ignore_case(true);
+        assert_eq!(Some(4..10), matcher.find_at("die Strase", 0));
+        assert!(!FuzzyMatcher::new("STRASSE", 1).is_match("die Strase"));
+    }
+}
@@ -0,0 +1,490 @@
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::io::{self, Write};
+
+use serde_json::json;
+
+use crate::{Match, Matcher};
+
+// the same ANSI colors grep uses by default
+const MATCH_COLOR: &str = "\x1b[1;31m";
+const FILE_NAME_COLOR: &str = "\x1b[35m";
+const LINE_NUMBER_COLOR: &str = "\x1b[32m";
+const SEPARATOR_COLOR: &str = "\x1b[36m";
+// grep has no pattern field, so this one's ours
+const PATTERN_COLOR: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+// how each line should be decorated and how much context to show around a hit
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PrintOptions {
+    pub line_number: bool,
+    // --show-pattern: which of the patterns matched, after the line number
+    pub show_pattern: bool,
+    pub before_context: usize,
+    pub after_context: usize,
+    // whether to write ANSI colors; run works this out from --color and whether stdout is a terminal
+    pub color: bool,
+    // --json; run sets this from the output mode, since it changes how every line is written
+    pub json: bool,
+}
+
+// what run prints for each file
+// -v isn't one of these since it changes which lines are selected rather than how they're reported, so -vc counts non-matching lines
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputMode {
+    // the selected lines themselves, with any context around them
+    #[default]
+    Lines,
+    // -c: just the number of selected lines per file
+    Count,
+    // -l: the names of files with at least one selected line
+    FilesWithMatches,
+    // -L: the names of files without any
+    FilesWithoutMatch,
+    // --json: one JSON object per selected line, then a summary object, for scripts that can't parse path:line reliably
+    Json,
+}
+
+// what the --json summary reports for each file
+#[derive(Debug, Default)]
+struct FileStats {
+    lines: usize,
+    matched_lines: usize,
+    matches: usize,
+}
+
+// turns the lines of a file into grep-style output
+// it's fed one line at a time, so it only ever keeps the last before_context lines around rather than the whole file
+pub struct Printer<W: Write> {
+    out: W,
+    options: PrintOptions,
+    name: String,
+    show_name: bool,
+    // lines held back in case a match turns up within the next before_context lines
+    pending: VecDeque<(usize, String)>,
+    after_left: usize,
+    last_printed: Option<usize>,
+    printed_any: bool,
+    // --json only: the counts for the current file, and those of the files already done
+    stats: FileStats,
+    finished: Vec<(String, FileStats)>,
+}
+
+impl<W: Write> Printer<W> {
+    pub fn new(out: W, options: PrintOptions) -> Printer<W> {
+        Printer {
+            out,
+            options,
+            name: String::new(),
+            show_name: false,
+            pending: VecDeque::new(),
+            after_left: 0,
+            last_printed: None,
+            printed_any: false,
+            stats: FileStats::default(),
+            finished: Vec::new(),
+        }
+    }
+
+    // resets the per-file state; show_name says whether each line should be prefixed with the file's name
+    pub fn begin_file(&mut self, name: &str, show_name: bool) {
+        self.name = name.to_string();
+        self.show_name = show_name;
+        self.pending.clear();
+        self.after_left = 0;
+        self.last_printed = None;
+        self.stats = FileStats::default();
+    }
+
+    pub fn end_file(&mut self) {
+        if self.options.json {
+            let stats = std::mem::take(&mut self.stats);
+            self.finished.push((self.name.clone(), stats));
+        }
+    }
+
+    // matcher is only used to find the spans to highlight when colors are on, and the patterns to name with --show-pattern
+    pub fn line(
+        &mut self,
+        number: usize,
+        line: &str,
+        is_match: bool,
+        matcher: &dyn Matcher,
+        patterns: &[String],
+    ) -> io::Result<()> {
+        if is_match {
+            while let Some((n, held)) = self.pending.pop_front() {
+                self.print(n, &held, '-', matcher, patterns)?;
+            }
+            self.print(number, line, ':', matcher, patterns)?;
+            self.after_left = self.options.after_context;
+        } else if self.after_left > 0 {
+            self.after_left -= 1;
+            self.print(number, line, '-', matcher, patterns)?;
+        } else if self.options.before_context > 0 {
+            if self.pending.len() == self.options.before_context {
+                self.pending.pop_front();
+            }
+            self.pending.push_back((number, line.to_string()));
+        }
+
+        Ok(())
+    }
+
+    // --json: every line goes through here so it can be counted, but only selected ones are written
+    // each gets the byte offsets of all the matches in it; with -v there are none
+    pub fn json_line(
+        &mut self,
+        found: Match<'_>,
+        is_match: bool,
+        matcher: &dyn Matcher,
+        patterns: &[String],
+    ) -> io::Result<()> {
+        self.stats.lines += 1;
+        if !is_match {
+            return Ok(());
+        }
+
+        let submatches: Vec<_> = matcher
+            .find_all(found.line)
+            .into_iter()
+            .map(|range| {
+                let pattern = matcher.pattern_of(found.line, range.clone());
+                json!({
+                    "start": range.start,
+                    "end": range.end,
+                    "text": &found.line[range],
+                    "pattern": patterns.get(pattern),
+                })
+            })
+            .collect();
+        self.stats.matched_lines += 1;
+        self.stats.matches += submatches.len();
+
+        let record = json!({
+            "type": "match",
+            "path": self.name,
+            "line_number": found.line_number,
+            "byte_offset": found.byte_offset,
+            "line": found.line,
+            "submatches": submatches,
+        });
+        writeln!(self.out, "{record}")
+    }
+
+    // -c: the number of selected lines in the current file, after its name if there's more than one file
+    pub fn count(&mut self, count: usize) -> io::Result<()> {
+        if self.show_name {
+            self.print_name(':')?;
+        }
+        writeln!(self.out, "{count}")
+    }
+
+    // -l and -L: the file name on its own
+    pub fn file_name(&mut self, name: &str) -> io::Result<()> {
+        self.paint(FILE_NAME_COLOR, name)?;
+        writeln!(self.out)
+    }
+
+    // a binary file had a selected line; its lines themselves would only garble the terminal, so just say so
+    pub fn binary_match(&mut self) -> io::Result<()> {
+        if self.options.json {
+            let record = json!({
+                "type": "binary",
+                "path": self.name,
+            });
+            return writeln!(self.out, "{record}");
+        }
+        writeln!(self.out, "Binary file {} matches", self.name)
+    }
+
+    pub fn options(&self) -> PrintOptions {
+        self.options
+    }
+
+    // takes over whatever another printer rendered for a whole file, as -j does,
+    // keeping the -- that a single printer would have put between its groups and the ones before it
+    pub fn append(&mut self, other: Printer<Vec<u8>>) -> io::Result<()> {
+        if other.printed_any {
+            if self.has_context() && self.printed_any {
+                self.separator()?;
+            }
+            self.printed_any = true;
+        }
+        self.finished.extend(other.finished);
+        self.out.write_all(&other.out)
+    }
+
+    // writes anything that has to wait until every file is done (the --json summary) and hands back the writer
+    pub fn finish(mut self, matched: bool) -> io::Result<W> {
+        if self.options.json {
+            let files: Vec<_> = self
+                .finished
+                .iter()
+                .map(|(path, stats)| {
+                    json!({
+                        "path": path,
+                        "lines": stats.lines,
+                        "matched_lines": stats.matched_lines,
+                        "matches": stats.matches,
+                    })
+                })
+                .collect();
+            let record = json!({
+                "type": "summary",
+                "matched": matched,
+                "files_searched": files.len(),
+                "matched_lines": self.finished.iter().map(|(_, s)| s.matched_lines).sum::<usize>(),
+                "matches": self.finished.iter().map(|(_, s)| s.matches).sum::<usize>(),
+                "files": files,
+            });
+            writeln!(self.out, "{record}")?;
+        }
+
+        Ok(self.out)
+    }
+
+    #[cfg(test)]
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    // grep marks matching lines with ':' and context lines with '-'
+    fn print(
+        &mut self,
+        number: usize,
+        line: &str,
+        sep: char,
+        matcher: &dyn Matcher,
+        patterns: &[String],
+    ) -> io::Result<()> {
+        // a gap since the last printed line (or a new file) starts a new group, and groups are split by --
+        if self.has_context()
+            && self.printed_any
+            && self.last_printed.map(|n| n + 1) != Some(number)
+        {
+            self.separator()?;
+        }
+
+        if self.show_name {
+            self.print_name(sep)?;
+        }
+        if self.options.line_number {
+            self.paint(LINE_NUMBER_COLOR, number)?;
+            self.paint(SEPARATOR_COLOR, sep)?;
+        }
+        if self.options.show_pattern {
+            // context lines, and lines selected by -v, matched nothing, but keep the (empty) field so the columns line up
+            let names = if sep == ':' {
+                pattern_names(line, matcher, patterns)
+            } else {
+                String::new()
+            };
+            self.paint(PATTERN_COLOR, names)?;
+            self.paint(SEPARATOR_COLOR, sep)?;
+        }
+        if self.options.color {
+            self.highlight(line, matcher)?;
+        } else {
+            write!(self.out, "{line}")?;
+        }
+        writeln!(self.out)?;
+
+        self.last_printed = Some(number);
+        self.printed_any = true;
+        Ok(())
+    }
+
+    fn print_name(&mut self, sep: char) -> io::Result<()> {
+        // take the name out for a moment so it can be painted while self is borrowed mutably
+        let name = std::mem::take(&mut self.name);
+        let result = self.paint(FILE_NAME_COLOR, &name);
+        self.name = name;
+        result?;
+        self.paint(SEPARATOR_COLOR, sep)
+    }
+
+    fn has_context(&self) -> bool {
+        self.options.before_context > 0 || self.options.after_context > 0
+    }
+
+    fn separator(&mut self) -> io::Result<()> {
+        self.paint(SEPARATOR_COLOR, "--")?;
+        writeln!(self.out)
+    }
+
+    // writes line with every match wrapped in color
+    fn highlight(&mut self, line: &str, matcher: &dyn Matcher) -> io::Result<()> {
+        let mut written = 0;
+
+        for found in matcher.find_all(line) {
+            write!(self.out, "{}", &line[written..found.start])?;
+            self.paint(MATCH_COLOR, &line[found.clone()])?;
+            written = found.end;
+        }
+
+        write!(self.out, "{}", &line[written..])
+    }
+
+    fn paint(&mut self, color: &str, text: impl Display) -> io::Result<()> {
+        if self.options.color {
+            write!(self.out, "{color}{text}{RESET}")
+        } else {
+            write!(self.out, "{text}")
+        }
+    }
+}
+
+// the patterns behind the matches in line, each named once in the order they first match, comma-separated
+fn pattern_names(line: &str, matcher: &dyn Matcher, patterns: &[String]) -> String {
+    let mut seen = Vec::new();
+    for range in matcher.find_all(line) {
+        let pattern = matcher.pattern_of(line, range);
+        if !seen.contains(&pattern) {
+            seen.push(pattern);
+        }
+    }
+
+    seen.iter()
+        .filter_map(|&pattern| patterns.get(pattern))
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LiteralMatcher, MultiLiteralMatcher};
+
+    fn render(options: PrintOptions, lines: &[(&str, bool)]) -> String {
+        let matcher = LiteralMatcher::new("o");
+        let mut printer = Printer::new(Vec::new(), options);
+        printer.begin_file("poem.txt", true);
+        for (i, (line, is_match)) in lines.iter().enumerate() {
+            let patterns = ["o".to_string()];
+            printer
+                .line(i + 1, line, *is_match, &matcher, &patterns)
+                .unwrap();
+        }
+        String::from_utf8(printer.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn context_groups_are_separated() {
+        let options = PrintOptions {
+            line_number: true,
+            before_context: 1,
+            after_context: 1,
+            ..PrintOptions::default()
+        };
+        let lines = [
+            ("one", false),
+            ("two", true),
+            ("three", false),
+            ("four", false),
+            ("five", false),
+            ("six", true),
+            ("seven", true),
+        ];
+
+        assert_eq!(
+            "\
+poem.txt-1-one
+poem.txt:2:two
+poem.txt-3-three
+--
+poem.txt-5-five
+poem.txt:6:six
+poem.txt:7:seven
+",
+            render(options, &lines)
+        );
+    }
+
+    #[test]
+    fn adjacent_groups_are_merged() {
+        let options = PrintOptions {
+            line_number: false,
+            before_context: 0,
+            after_context: 2,
+            ..PrintOptions::default()
+        };
+        let lines = [("a", true), ("b", false), ("c", true), ("d", false)];
+
+        assert_eq!(
+            "poem.txt:a\npoem.txt-b\npoem.txt:c\npoem.txt-d\n",
+            render(options, &lines)
+        );
+    }
+
+    #[test]
+    fn matches_are_highlighted() {
+        let options = PrintOptions {
+            line_number: true,
+            color: true,
+            ..PrintOptions::default()
+        };
+
+        assert_eq!(
+            "\x1b[35mpoem.txt\x1b[0m\x1b[36m:\x1b[0m\x1b[32m1\x1b[0m\x1b[36m:\x1b[0m\
+             t\x1b[1;31mo\x1b[0m t\x1b[1;31mo\x1b[0m\n",
+            render(options, &[("to to", true)])
+        );
+    }
+
+    #[test]
+    fn json_records_matches_and_summary() {
+        let options = PrintOptions {
+            json: true,
+            ..PrintOptions::default()
+        };
+        let patterns = ["o".to_string(), "tw".to_string()];
+        let matcher = MultiLiteralMatcher::new(&patterns).unwrap();
+        let mut printer = Printer::new(Vec::new(), options);
+
+        printer.begin_file("a:b.txt", false);
+        for found in crate::lines_with_positions("two: too\nnine\n") {
+            let is_match = matcher.is_match(found.line);
+            printer
+                .json_line(found, is_match, &matcher, &patterns)
+                .unwrap();
+        }
+        printer.end_file();
+        let output = String::from_utf8(printer.finish(true).unwrap()).unwrap();
+
+        let records: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            json!({
+                "type": "match",
+                "path": "a:b.txt",
+                "line_number": 1,
+                "byte_offset": 0,
+                "line": "two: too",
+                "submatches": [
+                    {"start": 0, "end": 2, "text": "tw", "pattern": "tw"},
+                    {"start": 2, "end": 3, "text": "o", "pattern": "o"},
+                    {"start": 6, "end": 7, "text": "o", "pattern": "o"},
+                    {"start": 7, "end": 8, "text": "o", "pattern": "o"},
+                ],
+            }),
+            records[0]
+        );
+        assert_eq!(
+            json!({
+                "type": "summary",
+                "matched": true,
+                "files_searched": 1,
+                "matched_lines": 1,
+                "matches": 4,
+                "files": [{"path": "a:b.txt", "lines": 2, "matched_lines": 1, "matches": 4}],
+            }),
+            records[1]
+        );
+        assert_eq!(2, records.len());
+    }
+}
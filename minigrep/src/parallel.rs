@@ -0,0 +1,96 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use crate::output::Printer;
+use crate::{display_name, search_path, Config, Matcher, MinigrepError, Searcher, Summary};
+
+// searches files on config.jobs worker threads while keeping the output exactly as a sequential run would print it
+// each worker renders a whole file into a buffer of its own; the calling thread writes the buffers out strictly in file order,
+// holding on to any that finish early until every file before them is done
+pub fn search_files<M: Matcher + Sync, W: Write>(
+    config: &Config,
+    searcher: &Searcher<M>,
+    files: &[PathBuf],
+    with_path: bool,
+    printer: &mut Printer<W>,
+    summary: &mut Summary,
+) -> Result<(), MinigrepError> {
+    // the pool is just a shared counter: each worker claims the next unsearched file until there are none left
+    let next_file = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    let print_options = printer.options();
+
+    // scoped threads can borrow config, searcher and files directly, and are all joined before this returns
+    thread::scope(|scope| {
+        for _ in 0..config.jobs.min(files.len()) {
+            let sender = sender.clone();
+            let next_file = &next_file;
+
+            scope.spawn(move || loop {
+                let index = next_file.fetch_add(1, Ordering::Relaxed);
+                let Some(path) = files.get(index) else {
+                    break;
+                };
+
+                let name = display_name(path);
+                let mut file_printer = Printer::new(Vec::new(), print_options);
+                let result =
+                    search_path(config, searcher, path, &name, with_path, &mut file_printer)
+                        .map_err(|e| e.at(path));
+
+                // the receiver only goes away if writing the output failed, so there's no point searching further
+                if sender.send((index, result, file_printer)).is_err() {
+                    break;
+                }
+            });
+        }
+        // only the workers' clones are left, so the loop below ends once they're all done
+        drop(sender);
+
+        let mut finished = BTreeMap::new();
+        let mut next_to_print = 0;
+
+        for (index, result, file_printer) in receiver {
+            finished.insert(index, (result, file_printer));
+
+            while let Some((result, file_printer)) = finished.remove(&next_to_print) {
+                printer
+                    .append(file_printer)
+                    .map_err(MinigrepError::Output)?;
+                summary.record(result)?;
+                next_to_print += 1;
+            }
+        }
+
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{run_with_summary, TempPath};
+    use std::fs;
+
+    #[test]
+    fn output_matches_a_sequential_run() {
+        let root = TempPath::new("parallel");
+        fs::create_dir_all(&root).unwrap();
+        for i in 0..20 {
+            let contents = format!("line {i}\nneedle {i}\nfiller\nfiller\nneedle again\n");
+            fs::write(root.join(format!("{i:02}.txt")), contents.repeat(i + 1)).unwrap();
+        }
+
+        let run_with = |jobs| run_with_summary(&["-n", "-C1", "-j", jobs, "needle", root.arg()]);
+
+        let (sequential_summary, sequential) = run_with("1");
+        let (parallel_summary, parallel) = run_with("4");
+
+        assert!(sequential.contains("--\n"));
+        assert_eq!(sequential, parallel);
+        assert_eq!(sequential_summary, parallel_summary);
+    }
+}
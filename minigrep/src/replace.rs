@@ -0,0 +1,295 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::{display_name, input, is_stdin, Config, Matcher, MinigrepError, Summary};
+
+// how many unchanged lines a diff hunk shows around the changed ones, like diff -u
+const CONTEXT: usize = 3;
+
+// --replace: rewrites every match in each file and prints the result as a unified diff,
+// or with --in-place writes it back to the file without printing anything
+// the whole file is needed either way (for the diff's line counts, and to write it back), so unlike a search it's read in one go
+pub fn replace_files<M: Matcher, W: Write>(
+    config: &Config,
+    matcher: &M,
+    replacement: &str,
+    files: &[PathBuf],
+    out: &mut W,
+    summary: &mut Summary,
+) -> Result<(), MinigrepError> {
+    for path in files {
+        let name = display_name(path);
+        let result = replace_path(config, matcher, replacement, path, &name, out);
+        summary.record(result)?;
+    }
+    Ok(())
+}
+
+// returns whether anything was replaced
+fn replace_path<M: Matcher, W: Write>(
+    config: &Config,
+    matcher: &M,
+    replacement: &str,
+    path: &Path,
+    name: &str,
+    out: &mut W,
+) -> Result<bool, MinigrepError> {
+    let not_editable = |reason| MinigrepError::NotEditable {
+        path: path.to_path_buf(),
+        reason,
+    };
+    let io = |source| MinigrepError::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+
+    if config.in_place && is_stdin(path) {
+        return Err(not_editable("standard input can't be edited in place"));
+    }
+
+    let mut input = input::open(path, config).map_err(io)?;
+    // writing the text back would silently leave the file uncompressed, or in a different encoding
+    if config.in_place && input.compressed {
+        return Err(not_editable("compressed files can't be edited in place"));
+    }
+    if config.in_place && input.transcoded {
+        return Err(not_editable(
+            "only plain UTF-8 files can be edited in place",
+        ));
+    }
+    let mut contents = Vec::new();
+    input.reader.read_to_end(&mut contents).map_err(io)?;
+
+    if contents.contains(&0) {
+        return Err(MinigrepError::Binary {
+            path: path.to_path_buf(),
+        });
+    }
+    let text = std::str::from_utf8(&contents).map_err(|e| {
+        let line_number = contents[..e.valid_up_to()]
+            .iter()
+            .filter(|&&b| b == b'\n')
+            .count()
+            + 1;
+        MinigrepError::InvalidUtf8 {
+            path: path.to_path_buf(),
+            line_number,
+        }
+    })?;
+
+    // line endings stay part of each line so that the file comes back exactly as it was, apart from the replacements
+    let old: Vec<&str> = text.split_inclusive('\n').collect();
+    let new: Vec<Option<String>> = old
+        .iter()
+        .map(|line| replace_line(matcher, line, replacement))
+        .collect();
+
+    if new.iter().all(Option::is_none) {
+        return Ok(false);
+    }
+
+    if config.in_place {
+        let rewritten: String = old
+            .iter()
+            .zip(&new)
+            .map(|(old, new)| new.as_deref().unwrap_or(old))
+            .collect();
+        write_atomically(path, &rewritten).map_err(io)?;
+    } else {
+        write_diff(out, name, &old, &new).map_err(MinigrepError::Output)?;
+    }
+
+    Ok(true)
+}
+
+// the line with every match replaced, or None if nothing in it matched
+fn replace_line<M: Matcher>(matcher: &M, line: &str, replacement: &str) -> Option<String> {
+    let content = line.strip_suffix('\n').unwrap_or(line);
+    let content = content.strip_suffix('\r').unwrap_or(content);
+
+    let ranges = matcher.find_all(content);
+    if ranges.is_empty() {
+        return None;
+    }
+
+    let mut replaced = String::with_capacity(line.len());
+    let mut last = 0;
+    for range in ranges {
+        replaced.push_str(&content[last..range.start]);
+        last = range.end;
+        matcher.replace_into(content, range, replacement, &mut replaced);
+    }
+    replaced.push_str(&line[last..]);
+
+    Some(replaced)
+}
+
+// prints the changes as a unified diff that patch -p0 can apply
+// old holds every line of the file and new the replacement for each line that changed
+fn write_diff<W: Write>(
+    out: &mut W,
+    name: &str,
+    old: &[&str],
+    new: &[Option<String>],
+) -> io::Result<()> {
+    writeln!(out, "--- {name}")?;
+    writeln!(out, "+++ {name}")?;
+
+    let changed: Vec<usize> = (0..old.len()).filter(|&i| new[i].is_some()).collect();
+    // how many more lines the new file has than the old one, up to where the current hunk starts
+    let mut shift: isize = 0;
+    let mut next = 0;
+
+    while next < changed.len() {
+        // changes close enough for their context to touch go in the same hunk
+        let mut last = next;
+        while last + 1 < changed.len() && changed[last + 1] - changed[last] <= 2 * CONTEXT + 1 {
+            last += 1;
+        }
+        let start = changed[next].saturating_sub(CONTEXT);
+        let end = (changed[last] + CONTEXT + 1).min(old.len());
+
+        let new_len: usize = (start..end)
+            .map(|i| match &new[i] {
+                Some(line) => line.split_inclusive('\n').count(),
+                None => 1,
+            })
+            .sum();
+        let old_len = end - start;
+        writeln!(
+            out,
+            "@@ -{} +{} @@",
+            hunk_range(start as isize, old_len),
+            hunk_range(start as isize + shift, new_len),
+        )?;
+
+        for i in start..end {
+            match &new[i] {
+                Some(line) => {
+                    write_diff_line(out, '-', old[i])?;
+                    for line in line.split_inclusive('\n') {
+                        write_diff_line(out, '+', line)?;
+                    }
+                }
+                None => write_diff_line(out, ' ', old[i])?,
+            }
+        }
+
+        shift += new_len as isize - old_len as isize;
+        next = last + 1;
+    }
+
+    Ok(())
+}
+
+// start is 0-based; an empty range names the line before it, as diff does
+fn hunk_range(start: isize, len: usize) -> String {
+    match len {
+        0 => format!("{start},0"),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{len}", start + 1),
+    }
+}
+
+fn write_diff_line<W: Write>(out: &mut W, prefix: char, line: &str) -> io::Result<()> {
+    write!(out, "{prefix}{line}")?;
+    if !line.ends_with('\n') {
+        write!(out, "\n\\ No newline at end of file\n")?;
+    }
+    Ok(())
+}
+
+// writes contents to a temp file next to path and renames it over path, so the file is either fully rewritten or left alone
+// a symlink is resolved first so its target gets rewritten rather than the link being replaced by a plain file
+fn write_atomically(path: &Path, contents: &str) -> io::Result<()> {
+    let path = fs::canonicalize(path)?;
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(".{file_name}.minigrep-{}", process::id()));
+
+    let result = (|| {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp)?;
+        file.write_all(contents.as_bytes())?;
+        file.set_permissions(fs::metadata(&path)?.permissions())?;
+        file.sync_all()?;
+        fs::rename(&temp, &path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{config, TempPath};
+    use crate::LiteralMatcher;
+
+    #[test]
+    fn diffs_only_show_changed_lines_and_their_context() {
+        let old: Vec<&str> = "1\n2\n3\n4 rust\n5\n6\n7\n8\n9\n10\n11\n12 rust\n13"
+            .split_inclusive('\n')
+            .collect();
+        let matcher = LiteralMatcher::new("rust");
+        let new: Vec<_> = old
+            .iter()
+            .map(|line| replace_line(&matcher, line, "crab\nclaws"))
+            .collect();
+
+        let mut out = Vec::new();
+        write_diff(&mut out, "nums.txt", &old, &new).unwrap();
+
+        assert_eq!(
+            "\
+--- nums.txt
++++ nums.txt
+@@ -1,7 +1,8 @@
+ 1
+ 2
+ 3
+-4 rust
++4 crab
++claws
+ 5
+ 6
+ 7
+@@ -9,5 +10,6 @@
+ 9
+ 10
+ 11
+-12 rust
++12 crab
++claws
+ 13
+\\ No newline at end of file
+",
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn rewrites_files_in_place_with_capture_groups() {
+        let path = TempPath::new("replace.txt");
+        fs::write(&path, "name = \"minigrep\"\r\nversion = \"0.1\"\n").unwrap();
+
+        let args = ["--regex", "--in-place", "--replace", "$2 = $1"];
+        let config = config(&[&args[..], &[r"(\w+) = (.*)", path.arg()]].concat());
+        let matcher = config.regex.clone().unwrap();
+
+        let mut out = Vec::new();
+        let replaced = replace_path(&config, &matcher, "$2 = $1", &path, "", &mut out).unwrap();
+
+        assert!(replaced);
+        assert!(out.is_empty());
+        assert_eq!(
+            "\"minigrep\" = name\r\n\"0.1\" = version\n",
+            fs::read_to_string(&path).unwrap()
+        );
+    }
+}
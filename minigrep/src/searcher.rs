@@ -0,0 +1,286 @@
+use std::borrow::Cow;
+use std::io::{self, BufRead};
+use std::ops::Range;
+
+use memchr::memmem;
+
+use crate::{Match, Matcher};
+
+/// A line selected by [`Searcher::search`].
+///
+/// Unlike [`Match`] it owns its text, since the reader's buffer is reused for the next line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineMatch {
+    pub line_number: usize,
+    pub byte_offset: usize,
+    pub line: String,
+    /// Where the matcher matched within `line`; empty for lines selected by `invert_match`.
+    pub ranges: Vec<Range<usize>>,
+    /// Which of the matcher's patterns each of `ranges` came from, see [`Matcher::pattern_of`].
+    pub patterns: Vec<usize>,
+}
+
+/// Searches any buffered reader line by line with a [`Matcher`], without printing anything.
+///
+/// This is what `run` is built on, for crates that want minigrep's search without its output.
+///
+/// ```
+/// use minigrep::{CaseInsensitiveMatcher, Searcher, WordMatcher};
+///
+/// let searcher = Searcher::new(WordMatcher::new(CaseInsensitiveMatcher::new("rust")));
+/// let found = searcher.search("Rust:\nTrust me.\nrust belt".as_bytes()).unwrap();
+///
+/// assert_eq!(vec![1, 3], found.iter().map(|m| m.line_number).collect::<Vec<_>>());
+/// assert_eq!(vec![0..4], found[1].ranges);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Searcher<M> {
+    matcher: M,
+    invert_match: bool,
+    lossy: bool,
+}
+
+impl<M: Matcher> Searcher<M> {
+    pub fn new(matcher: M) -> Searcher<M> {
+        Searcher {
+            matcher,
+            invert_match: false,
+            lossy: false,
+        }
+    }
+
+    /// Select the lines that don't match instead, like `grep -v`.
+    pub fn invert_match(mut self, invert_match: bool) -> Searcher<M> {
+        self.invert_match = invert_match;
+        self
+    }
+
+    /// Decode lines that aren't valid UTF-8 with U+FFFD in place of the bad bytes, instead of failing.
+    pub fn lossy(mut self, lossy: bool) -> Searcher<M> {
+        self.lossy = lossy;
+        self
+    }
+
+    pub fn matcher(&self) -> &M {
+        &self.matcher
+    }
+
+    pub fn is_selected(&self, line: &str) -> bool {
+        self.matcher.is_match(line) != self.invert_match
+    }
+
+    /// Collects every selected line of `reader`, along with where it matched.
+    pub fn search<R: BufRead>(&self, reader: R) -> io::Result<Vec<LineMatch>> {
+        let mut found = Vec::new();
+
+        self.search_with(reader, |line, selected| {
+            if selected {
+                let ranges = if self.invert_match {
+                    Vec::new()
+                } else {
+                    self.matcher.find_all(line.line)
+                };
+                let patterns = ranges
+                    .iter()
+                    .map(|range| self.matcher.pattern_of(line.line, range.clone()))
+                    .collect();
+                found.push(LineMatch {
+                    line_number: line.line_number,
+                    byte_offset: line.byte_offset,
+                    line: line.line.to_string(),
+                    ranges,
+                    patterns,
+                });
+            }
+            Ok::<_, io::Error>(true)
+        })?;
+
+        Ok(found)
+    }
+
+    /// Hands every line of `reader` to `sink`, selected or not, together with whether it was selected.
+    ///
+    /// One buffer is reused for all the lines, so memory use stays flat however big the input is.
+    /// `sink` returns `Ok(false)` to stop early. Unless the searcher is [`lossy`](Searcher::lossy),
+    /// a line that isn't valid UTF-8 ends the search with an `io::ErrorKind::InvalidData` error.
+    pub fn search_with<R, E, F>(&self, mut reader: R, mut sink: F) -> Result<(), E>
+    where
+        R: BufRead,
+        E: From<io::Error>,
+        F: FnMut(Match<'_>, bool) -> Result<bool, E>,
+    {
+        let mut buf = Vec::new();
+        let mut line_number = 0;
+        let mut byte_offset = 0;
+
+        loop {
+            buf.clear();
+            let read = reader.read_until(b'\n', &mut buf)?;
+            if read == 0 {
+                return Ok(());
+            }
+            line_number += 1;
+
+            let line = buf.strip_suffix(b"\n").unwrap_or(&buf);
+            let line = self.decode(line, line_number)?;
+
+            let found = Match {
+                line_number,
+                byte_offset,
+                line: &line,
+            };
+            if !sink(found, self.is_selected(&line))? {
+                return Ok(());
+            }
+
+            byte_offset += read;
+        }
+    }
+
+    /// Like [`search_with`](Searcher::search_with) for text that's already in memory, e.g. a memory-mapped file,
+    /// but only selected lines are handed to `sink`.
+    ///
+    /// When the matcher has a [`required_literal`](Matcher::required_literal) and lines aren't inverted,
+    /// the whole slice is scanned for it with a SIMD substring search, and only the lines it turns up in are looked at.
+    pub fn search_slice<E, F>(&self, haystack: &[u8], mut sink: F) -> Result<(), E>
+    where
+        E: From<io::Error>,
+        F: FnMut(Match<'_>, bool) -> Result<bool, E>,
+    {
+        let needle = match self.matcher.required_literal() {
+            // an empty needle is found everywhere, so it would be no quicker than going line by line
+            Some(needle) if !needle.is_empty() && !self.invert_match => needle,
+            _ => {
+                return self.search_with(haystack, |found, selected| {
+                    if selected {
+                        sink(found, selected)
+                    } else {
+                        Ok(true)
+                    }
+                })
+            }
+        };
+
+        let finder = memmem::Finder::new(needle);
+        // the line number is only worked out for lines that get looked at, by counting the newlines skipped since the last one
+        let mut line_number = 1;
+        let mut counted_to = 0;
+        let mut from = 0;
+
+        while let Some(at) = finder.find(&haystack[from..]).map(|i| from + i) {
+            let start = memchr::memrchr(b'\n', &haystack[..at]).map_or(0, |i| i + 1);
+            let end = memchr::memchr(b'\n', &haystack[at..]).map_or(haystack.len(), |i| at + i);
+
+            line_number += memchr::memchr_iter(b'\n', &haystack[counted_to..start]).count();
+            counted_to = start;
+
+            // the needle is only a hint, the matcher has the final say (e.g. about whole words)
+            let line = self.decode(&haystack[start..end], line_number)?;
+            if self.is_selected(&line) {
+                let found = Match {
+                    line_number,
+                    byte_offset: start,
+                    line: &line,
+                };
+                if !sink(found, true)? {
+                    return Ok(());
+                }
+            }
+
+            // the rest of this line is already dealt with
+            from = end + 1;
+            if from >= haystack.len() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    // a line without its \n, as text; a trailing \r is dropped too
+    fn decode<'a>(&self, line: &'a [u8], line_number: usize) -> io::Result<Cow<'a, str>> {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        match std::str::from_utf8(line) {
+            Ok(line) => Ok(Cow::Borrowed(line)),
+            Err(_) if self.lossy => Ok(String::from_utf8_lossy(line)),
+            Err(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {line_number} is not valid UTF-8"),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LiteralMatcher, RegexMatcher, WordMatcher};
+
+    #[test]
+    fn inverted_search_keeps_positions() {
+        let searcher = Searcher::new(LiteralMatcher::new("o")).invert_match(true);
+        let found = searcher.search("one\r\ntwo\nsix\nten".as_bytes()).unwrap();
+
+        assert_eq!(
+            vec![
+                LineMatch {
+                    line_number: 3,
+                    byte_offset: 9,
+                    line: "six".to_string(),
+                    ranges: vec![],
+                    patterns: vec![],
+                },
+                LineMatch {
+                    line_number: 4,
+                    byte_offset: 13,
+                    line: "ten".to_string(),
+                    ranges: vec![],
+                    patterns: vec![],
+                },
+            ],
+            found
+        );
+    }
+
+    #[test]
+    fn sink_can_stop_early() {
+        let searcher = Searcher::new(RegexMatcher::new("^t").unwrap());
+        let mut seen = Vec::new();
+
+        searcher
+            .search_with("one\ntwo\nthree".as_bytes(), |line, selected| {
+                seen.push(line.line_number);
+                Ok::<_, io::Error>(!selected)
+            })
+            .unwrap();
+
+        assert_eq!(vec![1, 2], seen);
+    }
+
+    #[test]
+    fn slices_find_the_same_lines_as_readers() {
+        let text = "rust\r\ntrust me\nnothing\n\nrusty rust\nno\nrust";
+        let searcher = Searcher::new(WordMatcher::new(LiteralMatcher::new("rust")));
+
+        let mut from_slice = Vec::new();
+        searcher
+            .search_slice(text.as_bytes(), |found, _| {
+                from_slice.push((found.line_number, found.byte_offset, found.line.to_string()));
+                Ok::<_, io::Error>(true)
+            })
+            .unwrap();
+
+        let from_reader: Vec<_> = searcher
+            .search(text.as_bytes())
+            .unwrap()
+            .into_iter()
+            .map(|m| (m.line_number, m.byte_offset, m.line))
+            .collect();
+
+        assert_eq!(from_reader, from_slice);
+        assert_eq!(
+            vec![1, 5, 7],
+            from_slice.iter().map(|m| m.0).collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,69 @@
+// helpers the test modules share, so each one doesn't grow its own copy
+
+use std::env;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::{run_to, Command, Config, Summary};
+
+// args as given on the command line, without the program name
+pub fn config(args: &[&str]) -> Config {
+    let args = ["minigrep"].iter().chain(args).map(|arg| arg.to_string());
+    match Config::build(args).unwrap() {
+        Command::Run(config) => *config,
+        Command::Help | Command::Version => panic!("expected a search"),
+    }
+}
+
+// runs minigrep with args and returns everything it printed
+pub fn run_with(args: &[&str]) -> String {
+    run_with_summary(args).1
+}
+
+pub fn run_with_summary(args: &[&str]) -> (Summary, String) {
+    let mut out = Vec::new();
+    let summary = run_to(&config(args), &mut out).unwrap();
+    (summary, String::from_utf8(out).unwrap())
+}
+
+// a file or directory in the temp dir that's removed again when dropped, so a failing test doesn't leave it behind
+// the name gets the process id added, so test runs side by side don't trip over each other
+pub struct TempPath(PathBuf);
+
+impl TempPath {
+    pub fn new(name: &str) -> TempPath {
+        TempPath(env::temp_dir().join(format!("minigrep-{}-{name}", process::id())))
+    }
+
+    // the path as an argument for run_with and config
+    pub fn arg(&self) -> &str {
+        self.0.to_str().unwrap()
+    }
+}
+
+impl Deref for TempPath {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempPath {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        // it may never have been created, so there's nothing to do about errors
+        let _ = if self.0.is_dir() {
+            fs::remove_dir_all(&self.0)
+        } else {
+            fs::remove_file(&self.0)
+        };
+    }
+}
@@ -0,0 +1,186 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use ignore::overrides::Override;
+use ignore::types::Types;
+use ignore::WalkBuilder;
+
+/// Which files a recursive search skips.
+///
+/// By default that's hidden files and directories plus anything `.gitignore`, `.ignore`
+/// or git's own exclude files list, the way ripgrep does it.
+#[derive(Debug, Clone)]
+pub struct WalkOptions {
+    // --hidden: search hidden files and directories too
+    pub hidden: bool,
+    // --no-ignore: don't read .gitignore, .ignore and the like
+    pub no_ignore: bool,
+    // --glob and --exclude; a file matching a --glob is searched even if an ignore file lists it
+    pub overrides: Override,
+    // --type
+    pub types: Types,
+}
+
+impl Default for WalkOptions {
+    fn default() -> WalkOptions {
+        WalkOptions {
+            hidden: false,
+            no_ignore: false,
+            overrides: Override::empty(),
+            types: Types::empty(),
+        }
+    }
+}
+
+// expands the paths given on the command line into the list of files to search
+// directories are walked recursively and their entries sorted by name so the output order is stable between runs
+// anything that can't be read is handed to on_error and skipped rather than aborting the whole walk
+// the filters in options only apply inside directories: a file named on the command line is always searched, like grep does
+pub fn collect_files(
+    paths: &[String],
+    options: &WalkOptions,
+    mut on_error: impl FnMut(&Path, io::Error),
+) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+
+    for path in paths {
+        let path = Path::new(path);
+
+        // - stands for stdin, which the caller reads itself
+        if path == Path::new("-") {
+            files.push(path.to_path_buf());
+            continue;
+        }
+
+        // paths named explicitly are followed even if they're symlinks, like grep does
+        match fs::metadata(path) {
+            Ok(meta) if meta.is_dir() => walk_dir(path, options, &mut files, &mut on_error),
+            Ok(_) => files.push(path.to_path_buf()),
+            Err(e) => on_error(path, e),
+        }
+    }
+
+    files
+}
+
+fn walk_dir(
+    dir: &Path,
+    options: &WalkOptions,
+    files: &mut Vec<PathBuf>,
+    on_error: &mut impl FnMut(&Path, io::Error),
+) {
+    // symlinks found while recursing are not followed (the builder's default) so a link back up the tree can't loop forever
+    // ignore files are honoured outside of git repos too, since plenty of trees get searched without a .git next to them
+    let walker = WalkBuilder::new(dir)
+        .standard_filters(!options.no_ignore)
+        .hidden(!options.hidden)
+        .require_git(false)
+        .overrides(options.overrides.clone())
+        .types(options.types.clone())
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .build();
+
+    for entry in walker {
+        match entry {
+            Ok(entry) if entry.file_type().is_some_and(|t| t.is_file()) => {
+                files.push(entry.into_path())
+            }
+            Ok(_) => {}
+            // the error knows which entry it was about more often than not; fall back on the directory being walked
+            Err(e) => {
+                let path = error_path(&e).unwrap_or(dir).to_path_buf();
+                let message = e.to_string();
+                on_error(
+                    &path,
+                    e.into_io_error()
+                        .unwrap_or_else(|| io::Error::other(message)),
+                );
+            }
+        }
+    }
+}
+
+fn error_path(e: &ignore::Error) -> Option<&Path> {
+    match e {
+        ignore::Error::WithPath { path, .. } => Some(path),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+            error_path(err)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{config, TempPath};
+
+    #[test]
+    fn walks_directories_recursively_in_sorted_order() {
+        let root = TempPath::new("walk");
+        fs::create_dir_all(root.join("b/nested")).unwrap();
+        fs::write(root.join("b/nested/deep.txt"), "deep").unwrap();
+        fs::write(root.join("b/one.txt"), "one").unwrap();
+        fs::write(root.join("a.txt"), "a").unwrap();
+
+        let missing = root.join("missing.txt");
+        let paths = [root.arg().to_string(), missing.display().to_string()];
+        let mut errors = Vec::new();
+        let files = collect_files(&paths, &WalkOptions::default(), |path, _| {
+            errors.push(path.to_path_buf())
+        });
+
+        assert_eq!(
+            vec![
+                root.join("a.txt"),
+                root.join("b/nested/deep.txt"),
+                root.join("b/one.txt"),
+            ],
+            files
+        );
+        assert_eq!(vec![missing], errors);
+    }
+
+    #[test]
+    fn skips_ignored_and_hidden_files_unless_asked() {
+        let root = TempPath::new("ignore");
+        fs::create_dir_all(root.join("target/debug")).unwrap();
+        fs::create_dir_all(root.join(".hidden")).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        fs::write(root.join("target/debug/build.rs"), "").unwrap();
+        fs::write(root.join(".hidden/secret.rs"), "").unwrap();
+        fs::write(root.join("src/main.rs"), "").unwrap();
+        fs::write(root.join("src/notes.md"), "").unwrap();
+
+        let paths = [root.arg().to_string()];
+        let collect = |args: &[&str]| {
+            let config = config(&[args, &["query", root.arg()]].concat());
+            collect_files(&paths, &config.walk_options, |path, e| {
+                panic!("{}: {e}", path.display())
+            })
+        };
+
+        assert_eq!(
+            vec![root.join("src/main.rs"), root.join("src/notes.md")],
+            collect(&[])
+        );
+        assert_eq!(
+            vec![
+                root.join(".gitignore"),
+                root.join(".hidden/secret.rs"),
+                root.join("src/main.rs"),
+                root.join("src/notes.md"),
+                root.join("target/debug/build.rs"),
+            ],
+            collect(&["--hidden", "--no-ignore"])
+        );
+        assert_eq!(vec![root.join("src/main.rs")], collect(&["-t", "rust"]));
+        assert_eq!(vec![root.join("src/notes.md")], collect(&["-g", "*.md"]));
+        assert_eq!(
+            vec![root.join("src/main.rs")],
+            collect(&["--exclude", "*.md"])
+        );
+    }
+}
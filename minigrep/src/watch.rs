@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::output::Printer;
+use crate::{
+    display_name, input, walk, warn, Config, LineSink, Matcher, MinigrepError, OutputMode,
+    SearchError, Searcher, Summary,
+};
+
+// how long to wait between looking for changes
+// polling needs no inotify limits or extra services, and twice a second is plenty for watching logs
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// --watch: searches the files, then keeps checking them for changes and searching again, until killed
+// only returns if writing the output fails
+pub fn watch<W: Write>(config: &Config, out: W) -> Result<Summary, MinigrepError> {
+    let mut watcher = Watcher::new(config, out);
+    loop {
+        watcher.poll()?;
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+// what the last poll saw of a file
+#[derive(Debug, Default)]
+struct FileState {
+    len: u64,
+    modified: Option<SystemTime>,
+    // which file the path led to, see file_id
+    id: Option<(u64, u64)>,
+    // how far the file has been searched, always just past a newline, and how many lines that was
+    offset: u64,
+    lines: usize,
+}
+
+pub struct Watcher<'a, W> {
+    config: &'a Config,
+    out: W,
+    files: HashMap<PathBuf, FileState>,
+    // what the last poll said about each path that couldn't be read, so one that stays broken is only warned about once
+    failing: HashMap<PathBuf, String>,
+}
+
+impl<'a, W: Write> Watcher<'a, W> {
+    pub fn new(config: &'a Config, out: W) -> Watcher<'a, W> {
+        Watcher {
+            config,
+            out,
+            files: HashMap::new(),
+            failing: HashMap::new(),
+        }
+    }
+
+    // one round of looking for changes; the first one searches everything
+    // in the plain lines mode a file that grew is only searched from where the last round stopped, so a growing log is tailed
+    // counts and file names are about whole files though, so in those modes any change means running the whole search again
+    pub fn poll(&mut self) -> Result<(), MinigrepError> {
+        let config = self.config;
+        let mut failed = Vec::new();
+        let paths =
+            walk::collect_files(&config.file_paths, &config.walk_options, |path, source| {
+                let err = MinigrepError::Io {
+                    path: path.to_path_buf(),
+                    source,
+                };
+                failed.push((path.to_path_buf(), err));
+            });
+        let with_path = paths.len() > 1 || config.file_paths.iter().any(|p| Path::new(p).is_dir());
+
+        // files that went away are forgotten, so they're searched from the start if they come back
+        let known = self.files.len();
+        self.files.retain(|path, _| paths.contains(path));
+        let removed = self.files.len() != known;
+
+        if config.output_mode != OutputMode::Lines {
+            let mut changed = removed;
+            for path in &paths {
+                let state = self.files.entry(path.clone()).or_default();
+                // a file that can't be read counts as changed; the search will say what's wrong with it
+                changed |= fs::metadata(path).map_or(true, |meta| state.update(&meta));
+            }
+            // a rerun warns about whatever it can't read itself, and it only happens when something changed
+            if changed {
+                crate::run_to(config, &mut self.out)?;
+            }
+            return Ok(());
+        }
+
+        let searcher = Searcher::new(config.matcher())
+            .invert_match(config.invert_match)
+            .lossy(true);
+        let mut print_options = config.print_options;
+        print_options.color = config.color.enabled(false);
+        let mut printer = Printer::new(&mut self.out, print_options);
+
+        for path in &paths {
+            let state = self.files.entry(path.clone()).or_default();
+            let name = display_name(path);
+            let result = tail(
+                config,
+                &searcher,
+                path,
+                &name,
+                state,
+                with_path,
+                &mut printer,
+            );
+            if let Err(e) = result {
+                match e.at(path) {
+                    err @ MinigrepError::Output(_) => return Err(err),
+                    err => failed.push((path.clone(), err)),
+                }
+            }
+        }
+
+        self.out.flush().map_err(MinigrepError::Output)?;
+        self.report(failed);
+        Ok(())
+    }
+
+    // warns about what couldn't be read this time round, except where the last poll already said the same thing
+    fn report(&mut self, failed: Vec<(PathBuf, MinigrepError)>) {
+        let mut failing = HashMap::new();
+        for (path, err) in failed {
+            let message = err.to_string();
+            if self.failing.get(&path) != Some(&message) {
+                warn(&err);
+            }
+            failing.insert(path, message);
+        }
+        self.failing = failing;
+    }
+}
+
+impl FileState {
+    // notes the file's current size and mtime, and returns whether either differs from last time
+    fn update(&mut self, meta: &fs::Metadata) -> bool {
+        let (len, modified) = (meta.len(), meta.modified().ok());
+        let changed = len != self.len || modified != self.modified;
+        self.len = len;
+        self.modified = modified;
+        changed
+    }
+}
+
+// prints the selected lines a file gained since the last poll, numbered as they are in the whole file
+// the file is opened the way a plain search opens it, so binary, compressed and non-UTF-8 files are treated the same here
+fn tail<M: Matcher, W: Write>(
+    config: &Config,
+    searcher: &Searcher<M>,
+    path: &Path,
+    name: &str,
+    state: &mut FileState,
+    with_path: bool,
+    printer: &mut Printer<W>,
+) -> Result<(), SearchError> {
+    let meta = fs::metadata(path)?;
+    let shrunk = meta.len() < state.offset;
+    let replaced = file_id(&meta) != state.id;
+    state.id = file_id(&meta);
+    if !state.update(&meta) && !replaced {
+        return Ok(());
+    }
+
+    // a file that got shorter, or a different file now at the same path (e.g. a log that was rotated),
+    // isn't the one that was searched before, so start over
+    // one that only got touched, or rewritten to the same length in place, just has nothing new past the offset
+    if shrunk || replaced {
+        state.offset = 0;
+        state.lines = 0;
+    }
+
+    let mut input = input::open(path, config)?;
+    // like search_reader, the start of the file decides whether it's binary, not the start of what was appended
+    let binary = !config.text && input.reader.fill_buf()?.contains(&0);
+
+    // a byte offset into a .gz or UTF-16 file means nothing once it's been decompressed or decoded,
+    // so those are read from the start every time and the lines already seen are skipped
+    // plain files are read from just past the last line searched instead, so a growing log costs only what it gained
+    let (reader, end, first_line): (Box<dyn BufRead>, u64, usize) =
+        if input.compressed || input.transcoded {
+            (input.reader, meta.len(), 0)
+        } else {
+            drop(input);
+            let mut file = File::open(path)?;
+            let end = end_of_last_line(&mut file, state.offset, state.len)?;
+            if end == state.offset {
+                return Ok(());
+            }
+            file.seek(SeekFrom::Start(state.offset))?;
+            let reader = BufReader::new(file.take(end - state.offset));
+            (Box::new(reader), end, state.lines)
+        };
+
+    printer.begin_file(name, with_path);
+    let mut sink = LineSink::new(config, searcher, printer, binary);
+    let mut last_line = state.lines;
+    searcher.search_with(reader, |mut found, is_selected| {
+        found.line_number += first_line;
+        if found.line_number <= state.lines {
+            return Ok(true);
+        }
+        last_line = found.line_number;
+        sink.line(found, is_selected)
+    })?;
+    printer.end_file();
+
+    state.offset = end;
+    state.lines = last_line;
+    Ok(())
+}
+
+// the device and inode a path leads to, which change when another file is moved into its place
+// other platforms make do with noticing that the file shrank
+#[cfg(unix)]
+fn file_id(meta: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_meta: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+// where the last complete line between from and len ends, just past its newline
+// a line that's still being written is left for a later poll, so it isn't printed half done
+fn end_of_last_line(file: &mut File, from: u64, len: u64) -> io::Result<u64> {
+    let mut block = [0; 8 * 1024];
+    let mut end = len;
+
+    // reading backwards from the end finds it without going through everything that was appended
+    while end > from {
+        let start = end.saturating_sub(block.len() as u64).max(from);
+        let block = &mut block[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(block)?;
+
+        if let Some(i) = memchr::memrchr(b'\n', block) {
+            return Ok(start + i as u64 + 1);
+        }
+        end = start;
+    }
+
+    Ok(from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{config, TempPath};
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::fs::OpenOptions;
+
+    #[test]
+    fn tails_appended_lines_and_starts_over_after_truncation() {
+        let path = TempPath::new("watch.log");
+        fs::write(&path, "ok\nERROR one\n").unwrap();
+
+        let config = config(&["-n", "ERROR", path.arg()]);
+        let mut watcher = Watcher::new(&config, Vec::new());
+        let poll = |watcher: &mut Watcher<Vec<u8>>| {
+            watcher.poll().unwrap();
+            String::from_utf8(std::mem::take(&mut watcher.out)).unwrap()
+        };
+        let append = |text: &str| {
+            let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+            file.write_all(text.as_bytes()).unwrap();
+        };
+
+        assert_eq!("2:ERROR one\n", poll(&mut watcher));
+        assert_eq!("", poll(&mut watcher));
+
+        // the half-written line waits until its newline shows up
+        append("ok\nERROR two\nERROR thr");
+        assert_eq!("4:ERROR two\n", poll(&mut watcher));
+        append("ee\n");
+        assert_eq!("5:ERROR three\n", poll(&mut watcher));
+
+        // a newer mtime alone means nothing was added
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+        assert_eq!("", poll(&mut watcher));
+
+        fs::write(&path, "ERROR anew\n").unwrap();
+        assert_eq!("1:ERROR anew\n", poll(&mut watcher));
+
+        // nor does a rotated log that happens to be as long as the old one get skipped
+        let rotated = TempPath::new("watch.log.new");
+        fs::write(&rotated, "ERROR anew\n").unwrap();
+        fs::rename(&rotated, &path).unwrap();
+        assert_eq!("1:ERROR anew\n", poll(&mut watcher));
+    }
+
+    #[test]
+    fn reads_compressed_and_binary_files_like_a_search_does() {
+        let gzip = |text: &str| {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(text.as_bytes()).unwrap();
+            encoder.finish().unwrap()
+        };
+        let path = TempPath::new("watch.log.gz");
+        fs::write(&path, gzip("ok\nERROR one\n")).unwrap();
+        let config = config(&["-n", "ERROR", path.arg()]);
+        let mut watcher = Watcher::new(&config, Vec::new());
+        let mut poll = || {
+            watcher.poll().unwrap();
+            String::from_utf8(std::mem::take(&mut watcher.out)).unwrap()
+        };
+
+        assert_eq!("2:ERROR one\n", poll());
+        // gzip members can be appended like lines, and only the new member's lines are printed
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&gzip("ERROR two\n")).unwrap();
+        assert_eq!("3:ERROR two\n", poll());
+
+        fs::write(&path, "ERROR\0 three\n").unwrap();
+        let binary = format!("Binary file {} matches\n", path.arg());
+        assert_eq!(binary, poll());
+    }
+
+    #[test]
+    fn remembers_unreadable_paths_until_they_can_be_read() {
+        let path = TempPath::new("watch-missing.log");
+        let config = config(&["ERROR", path.arg()]);
+        let mut watcher = Watcher::new(&config, Vec::new());
+
+        watcher.poll().unwrap();
+        let warned = watcher.failing.clone();
+        assert_eq!(vec![&path.to_path_buf()], warned.keys().collect::<Vec<_>>());
+        // the same error again isn't news
+        watcher.poll().unwrap();
+        assert_eq!(warned, watcher.failing);
+
+        fs::write(&path, "ERROR at last\n").unwrap();
+        watcher.poll().unwrap();
+        assert!(watcher.failing.is_empty());
+        assert_eq!(b"ERROR at last\n", &watcher.out[..]);
+    }
+}